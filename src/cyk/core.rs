@@ -0,0 +1,336 @@
+use crate::parser::core::Token;
+use crate::parser::tree::ParseTree;
+use crate::slr::helper::IndexedGrammar;
+use crate::transform::cnf::{to_cnf, Cnf, Restorer};
+use crate::utils::epsilon;
+use bnf::{Grammar, Term};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use log::debug;
+use std::fmt::{Display, Formatter};
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// A CYK parser, which accepts every context-free grammar by converting it into CNF
+pub struct Cyk<'grammar> {
+    pub(crate) grammar: &'grammar Grammar,
+    pub(crate) cnf: Cnf,
+}
+
+impl<'grammar> Cyk<'grammar> {
+    pub fn new(grammar: &'grammar Grammar) -> Cyk<'grammar> {
+        let cnf = to_cnf(grammar);
+        debug!("[CYK] CNF:\n{}", cnf);
+        Cyk { grammar, cnf }
+    }
+
+    pub fn cnf(&self) -> &Cnf {
+        &self.cnf
+    }
+
+    /// Fill the CYK table of `tokens`
+    pub fn parse<I>(&self, tokens: I) -> CykTable<'_, 'grammar, I::Item>
+    where
+        I: IntoIterator,
+        I::Item: Token,
+    {
+        CykTable::new(self, tokens.into_iter().collect())
+    }
+
+    /// Whether the start symbol derives `tokens`
+    pub fn recognize<I>(&self, tokens: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Token,
+    {
+        self.parse(tokens).is_member()
+    }
+}
+
+/// How a nonterminal of the CNF grammar derives the tokens of a cell
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Derivation {
+    /// `A -> a`, the index of the production
+    Terminal(usize),
+    /// `A -> B C`, the index of the production,
+    /// `B` derives the first `split` tokens and `C` the rest
+    Split(usize, usize),
+}
+
+/// The triangular CYK table of an input, `cell(len, start)` holds every nonterminal
+/// of the CNF grammar which derives the `len` tokens from `start`
+pub struct CykTable<'cyk, 'grammar, T> {
+    cyk: &'cyk Cyk<'grammar>,
+    cnf: IndexedGrammar<'cyk>,
+    tokens: Vec<T>,
+    /// `cells[len - 1][start]`
+    cells: Vec<Vec<IndexMap<&'cyk Term, Vec<Derivation>>>>,
+}
+
+impl<'cyk, 'grammar, T: Token> CykTable<'cyk, 'grammar, T> {
+    fn new(cyk: &'cyk Cyk<'grammar>, tokens: Vec<T>) -> CykTable<'cyk, 'grammar, T> {
+        let cnf = IndexedGrammar::new(cyk.cnf.grammar());
+        let n = tokens.len();
+        let mut cells = (1..=n)
+            .map(|len| vec![IndexMap::new(); n + 1 - len])
+            .collect::<Vec<Vec<IndexMap<&Term, Vec<Derivation>>>>>();
+
+        // A -> a
+        for (start, token) in tokens.iter().enumerate() {
+            for (index, (lhs, rhs)) in cnf.iter() {
+                if rhs.terms_iter().eq(std::iter::once(token.terminal())) {
+                    let cell: &mut Vec<Derivation> = cells[0][start].entry(lhs).or_default();
+                    cell.push(Derivation::Terminal(index));
+                }
+            }
+        }
+        // A -> B C
+        for len in 2..=n {
+            for start in 0..=n - len {
+                for split in 1..len {
+                    for (index, (lhs, rhs)) in cnf.iter() {
+                        let Some((b, c)) = rhs.terms_iter().collect_tuple() else {
+                            continue;
+                        };
+                        if cells[split - 1][start].contains_key(b)
+                            && cells[len - split - 1][start + split].contains_key(c)
+                        {
+                            debug!(
+                                "[CYK] {} derives {}..{} by r{}, split at {}",
+                                lhs,
+                                start,
+                                start + len,
+                                index,
+                                split
+                            );
+                            let cell: &mut Vec<Derivation> =
+                                cells[len - 1][start].entry(lhs).or_default();
+                            cell.push(Derivation::Split(index, split));
+                        }
+                    }
+                }
+            }
+        }
+
+        CykTable {
+            cyk,
+            cnf,
+            tokens,
+            cells,
+        }
+    }
+
+    /// The start symbol of the CNF grammar
+    fn start(&self) -> Option<&'cyk Term> {
+        self.cyk
+            .cnf
+            .grammar()
+            .productions_iter()
+            .next()
+            .map(|production| &production.lhs)
+    }
+
+    /// The nonterminals of the CNF grammar which derive the `len` tokens from `start`
+    pub fn cell(&self, len: usize, start: usize) -> impl Iterator<Item = &'cyk Term> + '_ {
+        len.checked_sub(1)
+            .and_then(|len| self.cells.get(len))
+            .and_then(|row| row.get(start))
+            .into_iter()
+            .flat_map(|cell| cell.keys().copied())
+    }
+
+    /// Whether the start symbol derives the whole input
+    pub fn is_member(&self) -> bool {
+        match self.tokens.len() {
+            0 => self.epsilon().is_some(),
+            n => self
+                .start()
+                .is_some_and(|start| self.cells[n - 1][0].contains_key(start)),
+        }
+    }
+
+    /// The production `S -> 'ε'` of the CNF grammar, if any
+    fn epsilon(&self) -> Option<usize> {
+        let start = self.start()?;
+        self.cnf
+            .iter()
+            .find(|(_, (lhs, rhs))| {
+                *lhs == start && rhs.terms_iter().eq(std::iter::once(epsilon()))
+            })
+            .map(|(index, _)| index)
+    }
+
+    /// Every parse tree of the CNF grammar of `lhs` deriving the `len` tokens from `start`
+    fn cnf_trees(
+        &self,
+        lhs: &'cyk Term,
+        len: usize,
+        start: usize,
+        all: bool,
+    ) -> Vec<ParseTree<'cyk>> {
+        let Some(derivations) = self.cells[len - 1][start].get(lhs) else {
+            return vec![];
+        };
+        let mut trees = vec![];
+        for derivation in derivations {
+            match *derivation {
+                Derivation::Terminal(index) => {
+                    let (lhs, rhs) = self.cnf.get_index(index).unwrap();
+                    let leaf = ParseTree::leaf(&self.tokens[start]);
+                    trees.push(ParseTree::node(lhs, rhs, index, vec![leaf]));
+                }
+                Derivation::Split(index, split) => {
+                    let (lhs, rhs) = self.cnf.get_index(index).unwrap();
+                    let (b, c) = rhs.terms_iter().collect_tuple().unwrap();
+                    let lefts = self.cnf_trees(b, split, start, all);
+                    let rights = self.cnf_trees(c, len - split, start + split, all);
+                    for (left, right) in lefts.iter().cartesian_product(rights.iter()) {
+                        let children = vec![left.clone(), right.clone()];
+                        trees.push(ParseTree::node(lhs, rhs, index, children));
+                        if !all {
+                            return trees;
+                        }
+                    }
+                }
+            }
+            if !all && !trees.is_empty() {
+                break;
+            }
+        }
+        trees
+    }
+
+    fn trees(&self, all: bool) -> Vec<ParseTree<'grammar>> {
+        if !self.is_member() {
+            return vec![];
+        }
+        let trees = match self.tokens.len() {
+            0 => {
+                let index = self.epsilon().unwrap();
+                let (lhs, rhs) = self.cnf.get_index(index).unwrap();
+                vec![ParseTree::node(lhs, rhs, index, vec![])]
+            }
+            n => self.cnf_trees(self.start().unwrap(), n, 0, all),
+        };
+        let Some(restorer) = Restorer::new(self.cyk.grammar) else {
+            return vec![];
+        };
+        // every tree of the CNF grammar maps back, a tree which does not is dropped
+        trees
+            .iter()
+            .filter_map(|tree| restorer.restore(&self.cyk.cnf.unbinarize(tree)?))
+            .collect()
+    }
+
+    /// A parse tree of the original grammar, `None` if the input is not in the language
+    pub fn parse_tree(&self) -> Option<ParseTree<'grammar>> {
+        self.trees(false).pop()
+    }
+
+    /// Every parse tree of the CNF grammar, mapped back to the original grammar
+    ///
+    /// The simplification into CNF may merge some derivations of the original grammar,
+    /// the trees are distinct derivations of the CNF grammar
+    pub fn parse_trees(&self) -> Vec<ParseTree<'grammar>> {
+        self.trees(true)
+    }
+
+    /// The triangular table, one row per length, one column per starting token
+    pub fn tabled(&self) -> Table {
+        let mut builder = Builder::default();
+        builder.push_record(
+            std::iter::once(String::from("Length/Token"))
+                .chain(self.tokens.iter().map(|token| token.text().to_string())),
+        );
+        for (len, row) in self.cells.iter().enumerate() {
+            builder.push_record(std::iter::once((len + 1).to_string()).chain(
+                (0..self.tokens.len()).map(|start| {
+                    row.get(start)
+                        .map(|cell| cell.keys().join(", "))
+                        .unwrap_or_default()
+                }),
+            ));
+        }
+        builder.build()
+    }
+}
+
+impl<'cyk, 'grammar, T: Token> Display for CykTable<'cyk, 'grammar, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("CYK Table: \n{}", self.tabled()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cyk::core::Cyk;
+    use crate::parser::core::{tokens, Lexeme};
+    use crate::utils::language::sentences;
+    use bnf::Grammar;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <E> ::= <E> '+' <E> | <E> '*' <E> | '(' <E> ')' | 'id'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn it_works() {
+        let grammar = grammar();
+        let cyk = Cyk::new(&grammar);
+
+        let table = cyk.parse(Lexeme::split_whitespace("id + id * id"));
+        assert!(table.is_member());
+        insta::assert_snapshot!(table);
+        insta::assert_snapshot!(table.parse_tree().unwrap());
+
+        assert!(!cyk.recognize(tokens("id + * id")));
+        assert!(!cyk.recognize(tokens("")));
+    }
+
+    #[test]
+    fn ambiguous() {
+        let grammar = grammar();
+        let cyk = Cyk::new(&grammar);
+
+        // (id + id) * id and id + (id * id)
+        let trees = cyk.parse(tokens("id + id * id")).parse_trees();
+        assert_eq!(trees.len(), 2);
+        assert_ne!(trees[0], trees[1]);
+        // Catalan(3) ways to bracket 4 operands
+        assert_eq!(
+            cyk.parse(tokens("id + id + id + id")).parse_trees().len(),
+            5
+        );
+    }
+
+    #[test]
+    fn restore() {
+        let grammar: Grammar = r#"
+        <S> ::= <A> <S> 'b' | <C>
+        <A> ::= 'a' | 'ε'
+        <C> ::= 'ε'
+        "#
+        .parse()
+        .unwrap();
+        let cyk = Cyk::new(&grammar);
+
+        // every tree is a parse tree of the original grammar
+        let tree = cyk.parse(tokens("a b b")).parse_tree().unwrap();
+        assert_eq!(tree.text(), "a b b");
+        insta::assert_snapshot!(tree);
+
+        let tree = cyk.parse(tokens("")).parse_tree().unwrap();
+        assert_eq!(
+            tree.symbol(),
+            &grammar.productions_iter().next().unwrap().lhs
+        );
+        assert!(tree.children().iter().all(|child| child.text().is_empty()));
+
+        for sentence in sentences(&grammar, 5) {
+            assert!(cyk.recognize(sentence));
+        }
+    }
+}
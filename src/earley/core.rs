@@ -0,0 +1,444 @@
+use crate::error::Result;
+use crate::ir::grammar::InternedGrammar;
+use crate::ir::symbol::SymbolId;
+use crate::lr0::core::LR0Item;
+use crate::parser::core::Token;
+use crate::parser::forest::{NodeId, ParseForest};
+use crate::parser::tree::ParseTree;
+use crate::utils::first::First;
+use bnf::Grammar;
+use indexmap::IndexSet;
+use itertools::Itertools;
+use log::debug;
+use std::fmt::{Display, Formatter};
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// An Earley parser, which accepts every context-free grammar as is,
+/// the lhs of the first production is the start symbol
+pub struct Earley {
+    pub(crate) grammar: InternedGrammar,
+    /// only whether a symbol is nullable is used
+    pub(crate) first: First,
+}
+
+/// An LR(0) item `lhs -> α • β` predicted at the token `origin`
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct EarleyItem {
+    pub(crate) lr0: LR0Item,
+    pub(crate) origin: usize,
+}
+
+impl EarleyItem {
+    pub fn lr0(&self) -> &LR0Item {
+        &self.lr0
+    }
+
+    pub fn origin(&self) -> usize {
+        self.origin
+    }
+
+    /// Whether the dot is at the end of the rhs
+    pub fn is_complete(&self, grammar: &InternedGrammar) -> bool {
+        self.lr0.expect(grammar).is_none()
+    }
+
+    fn advance(&self) -> EarleyItem {
+        EarleyItem {
+            lr0: self.lr0.advance(),
+            ..*self
+        }
+    }
+
+    /// `[lhs -> α • β, origin]` with the terms of `grammar`
+    pub fn display(&self, grammar: &InternedGrammar) -> String {
+        format!("[{}, {}]", self.lr0.display(grammar), self.origin)
+    }
+}
+
+impl Earley {
+    pub fn new(grammar: &Grammar) -> Earley {
+        let grammar = InternedGrammar::new(grammar);
+        let first = First::new(&grammar);
+        Earley { grammar, first }
+    }
+
+    /// Same as [`Earley::new`], but fail if the grammar is empty or uses an undefined nonterminal
+    pub fn try_new(grammar: &Grammar) -> Result<Earley> {
+        let grammar = InternedGrammar::try_new(grammar)?;
+        let first = First::new(&grammar);
+        Ok(Earley { grammar, first })
+    }
+
+    pub fn grammar(&self) -> &InternedGrammar {
+        &self.grammar
+    }
+
+    /// Fill the Earley sets of `tokens`
+    pub fn parse<I>(&self, tokens: I) -> EarleyChart<'_, I::Item>
+    where
+        I: IntoIterator,
+        I::Item: Token,
+    {
+        EarleyChart::new(self, tokens.into_iter().collect())
+    }
+
+    /// Whether the start symbol derives `tokens`
+    pub fn recognize<I>(&self, tokens: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Token,
+    {
+        self.parse(tokens).is_member()
+    }
+
+    /// The items `symbol -> • 𝛾` predicted at `origin`
+    fn predict(&self, symbol: SymbolId, origin: usize) -> impl Iterator<Item = EarleyItem> + '_ {
+        self.grammar
+            .productions_of(symbol)
+            .map(move |(production, _)| EarleyItem {
+                lr0: LR0Item::new(production),
+                origin,
+            })
+    }
+}
+
+/// The Earley sets of an input, the `k`-th set holds the items
+/// whose symbols before the dot derive the tokens `origin..k`
+pub struct EarleyChart<'earley, T> {
+    earley: &'earley Earley,
+    tokens: Vec<T>,
+    sets: Vec<IndexSet<EarleyItem>>,
+}
+
+impl<'earley, T: Token> EarleyChart<'earley, T> {
+    fn new(earley: &'earley Earley, tokens: Vec<T>) -> EarleyChart<'earley, T> {
+        let grammar = &earley.grammar;
+        let mut sets = vec![IndexSet::new(); tokens.len() + 1];
+        if let Some(start) = grammar.start() {
+            sets[0].extend(earley.predict(start, 0));
+        }
+
+        for k in 0..sets.len() {
+            let mut i = 0;
+            while let Some(item) = sets[k].get_index(i).copied() {
+                i += 1;
+                match item.lr0.expect(grammar) {
+                    // complete, advance every item of the origin waiting for lhs
+                    None => {
+                        let lhs = item.lr0.lhs(grammar);
+                        let waiting = sets[item.origin]
+                            .iter()
+                            .filter(|waiting| waiting.lr0.expect(grammar) == Some(lhs))
+                            .map(|waiting| waiting.advance())
+                            .collect::<Vec<_>>();
+                        for advanced in waiting {
+                            if sets[k].insert(advanced) {
+                                debug!(
+                                    "[Earley] S_{}: complete {} by {}",
+                                    k,
+                                    advanced.display(grammar),
+                                    item.display(grammar)
+                                );
+                            }
+                        }
+                    }
+                    Some(symbol) if grammar.symbols().is_nonterminal(symbol) => {
+                        for predicted in earley.predict(symbol, k) {
+                            if sets[k].insert(predicted) {
+                                debug!("[Earley] S_{}: predict {}", k, predicted.display(grammar));
+                            }
+                        }
+                        // Aycock–Horspool, a nullable nonterminal may be completed
+                        // before the item waiting for it is added
+                        if earley.first.is_nullable(symbol) {
+                            sets[k].insert(item.advance());
+                        }
+                    }
+                    Some(symbol) => {
+                        let term = grammar.term(symbol);
+                        if tokens.get(k).is_some_and(|token| token.terminal() == term) {
+                            debug!("[Earley] S_{}: scan {}", k, term);
+                            sets[k + 1].insert(item.advance());
+                        }
+                    }
+                }
+            }
+        }
+
+        EarleyChart {
+            earley,
+            tokens,
+            sets,
+        }
+    }
+
+    /// The items of the `k`-th set
+    pub fn set(&self, k: usize) -> impl Iterator<Item = &EarleyItem> {
+        self.sets.get(k).into_iter().flatten()
+    }
+
+    /// Whether the start symbol derives the whole input
+    pub fn is_member(&self) -> bool {
+        self.earley
+            .grammar
+            .start()
+            .is_some_and(|start| self.completed(start, 0, self.tokens.len()).next().is_some())
+    }
+
+    /// The position of the first token which cannot be scanned,
+    /// `None` if every token can be, even if the input is not in the language
+    pub fn error_position(&self) -> Option<usize> {
+        (1..self.sets.len())
+            .find(|k| self.sets[*k].is_empty())
+            .map(|k| k - 1)
+    }
+
+    /// The completed items of `lhs` in the set `end` predicted at `start`
+    fn completed(
+        &self,
+        lhs: SymbolId,
+        start: usize,
+        end: usize,
+    ) -> impl Iterator<Item = &EarleyItem> {
+        let grammar = &self.earley.grammar;
+        self.set(end).filter(move |item| {
+            item.origin == start && item.lr0.lhs(grammar) == lhs && item.is_complete(grammar)
+        })
+    }
+
+    /// The shared packed parse forest of every derivation of the input
+    pub fn forest(&self) -> ParseForest<'earley> {
+        let mut forest = ParseForest::new();
+        if let Some(start) = self.earley.grammar.start().filter(|_| self.is_member()) {
+            forest.root = Some(self.symbol(&mut forest, start, 0, self.tokens.len()));
+        }
+        forest
+    }
+
+    /// A parse tree of the input, `None` if the input is not in the language
+    pub fn parse_tree(&self) -> Option<ParseTree<'earley>> {
+        self.forest().tree()
+    }
+
+    /// The node of `symbol` deriving `start..end`, with all its derivations
+    fn symbol(
+        &self,
+        forest: &mut ParseForest<'earley>,
+        symbol: SymbolId,
+        start: usize,
+        end: usize,
+    ) -> NodeId {
+        let grammar: &'earley InternedGrammar = &self.earley.grammar;
+        if grammar.symbols().is_terminal(symbol) {
+            return forest.leaf(start, &self.tokens[start]);
+        }
+        let (id, created) = forest.symbol(grammar.term(symbol), start, end);
+        if created {
+            let items = self
+                .completed(symbol, start, end)
+                .map(|item| item.lr0)
+                .collect::<Vec<_>>();
+            for lr0 in items {
+                let dot = lr0.delimiter;
+                self.derive(forest, id, &lr0, dot, start, end);
+            }
+        }
+        id
+    }
+
+    /// Add to `parent` every way the first `dot` symbols of `lr0` derive `start..end`
+    fn derive(
+        &self,
+        forest: &mut ParseForest<'earley>,
+        parent: NodeId,
+        lr0: &LR0Item,
+        dot: usize,
+        start: usize,
+        end: usize,
+    ) {
+        let grammar: &'earley InternedGrammar = &self.earley.grammar;
+        let production = lr0.production.index();
+        let (lhs, rhs) = grammar.rule(lr0.production);
+        let Some(last) = grammar
+            .production(lr0.production)
+            .rhs
+            .get(dot.wrapping_sub(1))
+            .copied()
+        else {
+            // ε-production
+            forest.pack(parent, lhs, rhs, production, None, None);
+            return;
+        };
+        for split in start..=end {
+            if !self.derives(last, split, end) || !self.prefix(lr0, dot - 1, start, split) {
+                continue;
+            }
+            let left = match dot - 1 {
+                0 => None,
+                _ => {
+                    let (id, created) =
+                        forest.intermediate(lhs, rhs, production, dot - 1, start, split);
+                    if created {
+                        self.derive(forest, id, lr0, dot - 1, start, split);
+                    }
+                    Some(id)
+                }
+            };
+            let right = self.symbol(forest, last, split, end);
+            forest.pack(parent, lhs, rhs, production, left, Some(right));
+        }
+    }
+
+    /// Whether `symbol` derives the tokens `start..end`
+    fn derives(&self, symbol: SymbolId, start: usize, end: usize) -> bool {
+        let grammar = &self.earley.grammar;
+        match grammar.symbols().is_terminal(symbol) {
+            true => start + 1 == end && self.tokens[start].terminal() == grammar.term(symbol),
+            false => self.completed(symbol, start, end).next().is_some(),
+        }
+    }
+
+    /// Whether the first `dot` symbols of `lr0` derive the tokens `start..end`
+    fn prefix(&self, lr0: &LR0Item, dot: usize, start: usize, end: usize) -> bool {
+        let item = EarleyItem {
+            lr0: LR0Item {
+                delimiter: dot,
+                ..*lr0
+            },
+            origin: start,
+        };
+        self.sets[end].contains(&item)
+    }
+
+    /// One row per Earley set, with the token scanned into it
+    pub fn tabled(&self) -> Table {
+        let grammar = &self.earley.grammar;
+        let mut builder = Builder::default();
+        builder.push_record(["Set", "Token", "Items"]); // header
+        for (k, set) in self.sets.iter().enumerate() {
+            let token = match k {
+                0 => String::new(),
+                _ => self.tokens[k - 1].text().to_string(),
+            };
+            builder.push_record([
+                format!("S_{}", k),
+                token,
+                set.iter().map(|item| item.display(grammar)).join("\n"),
+            ]);
+        }
+        builder.build()
+    }
+}
+
+impl<'earley, T: Token> Display for EarleyChart<'earley, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Earley Sets: \n{}", self.tabled()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::earley::core::Earley;
+    use crate::parser::core::{tokens, Lexeme, Parser};
+    use crate::slr::builder::SLRTableBuilder;
+    use bnf::{Grammar, Production};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <E> ::= <E> '+' <E> | <E> '*' <E> | '(' <E> ')' | 'id'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn it_works() {
+        let grammar = grammar();
+        let earley = Earley::new(&grammar);
+
+        let chart = earley.parse(tokens("id + id"));
+        assert!(chart.is_member());
+        insta::assert_snapshot!(chart);
+
+        let chart = earley.parse(Lexeme::split_whitespace("id + id * id"));
+        let forest = chart.forest();
+        assert!(forest.is_ambiguous());
+        insta::assert_snapshot!(forest);
+        insta::assert_snapshot!(chart.parse_tree().unwrap());
+
+        let chart = earley.parse(tokens("id + * id"));
+        assert!(!chart.is_member());
+        assert_eq!(chart.error_position(), Some(2));
+        assert!(chart.forest().root().is_none());
+        assert!(!earley.recognize(tokens("id +")));
+    }
+
+    #[test]
+    fn epsilon() {
+        // a naive Earley parser never completes <B>, predicted after <A> is completed
+        let grammar: Grammar = r#"
+        <S> ::= <A> <B> 'c'
+        <A> ::= 'ε'
+        <B> ::= <A> | 'b'
+        "#
+        .parse()
+        .unwrap();
+        let earley = Earley::new(&grammar);
+
+        assert!(earley.recognize(tokens("b c")));
+        let tree = earley.parse(tokens("c")).parse_tree().unwrap();
+        assert_eq!(tree.text(), "c");
+        insta::assert_snapshot!(tree);
+        assert!(!earley.recognize(tokens("")));
+    }
+
+    #[test]
+    fn cycle() {
+        let grammar: Grammar = r#"
+        <S> ::= <S> | 'a'
+        "#
+        .parse()
+        .unwrap();
+        let earley = Earley::new(&grammar);
+
+        let forest = earley.parse(tokens("a")).forest();
+        assert!(forest.is_ambiguous());
+        let tree = forest.tree().unwrap();
+        assert_eq!(tree.children().len(), 1);
+        assert_eq!(tree.text(), "a");
+    }
+
+    #[test]
+    fn oracle() {
+        let grammar: Grammar = r#"
+        <E'> ::= <E>
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= <T> '*' <F> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let parser = Parser::new(&slr);
+        let earley = Earley::new(&grammar);
+
+        for input in [
+            "id",
+            "id + id * id",
+            "( id + id ) * id",
+            "id * ( id",
+            "id id",
+            "( ( id ) )",
+        ] {
+            let tree = earley.parse(tokens(input)).parse_tree();
+            match parser.parse_tree(tokens(input)) {
+                // the augmentation <E'> -> <E> is never reduced by the LR parser
+                Ok(expected) => assert_eq!(tree.unwrap().children(), [expected]),
+                Err(_) => assert!(tree.is_none()),
+            }
+        }
+    }
+}
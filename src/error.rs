@@ -0,0 +1,230 @@
+use crate::ll1::core::LL1Conflict;
+use crate::slr::conflict::{Conflict, ConflictKind};
+use crate::slr::core::SLRInstruction;
+use crate::utils::augmentation::AugmentationError;
+use bnf::{Production, Term};
+use itertools::Itertools;
+use std::fmt::{Display, Formatter};
+
+/// Everything that can go wrong when a grammar is analysed or a table is built
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// the grammar has no production at all
+    EmptyGrammar,
+    /// a nonterminal is used on a right-hand side but defined by no production
+    UndefinedNonterminal(Term),
+    /// the start symbol is defined by no production
+    StartSymbolMissing(Term),
+    /// the augmentation `<S'> ::= <S>` does not fit the grammar
+    Augmentation(AugmentationError),
+    /// the `alternative`-th alternative of `lhs` is referred to but not in the grammar
+    UndefinedProduction { lhs: Term, alternative: usize },
+    /// a terminal of the grammar is `'$'`, which is reserved for the end of input
+    ReservedTerminal(Term),
+    /// goto(I_state, term) is expected but missing from the LR automaton
+    MissingTransition { state: usize, term: Term },
+    /// a cell of an LR table has more than one action
+    LRConflict {
+        state: usize,
+        lookahead: Term,
+        kind: ConflictKind,
+        actions: Vec<SLRInstruction>,
+    },
+    /// a cell of an LL(1) table has more than one production
+    LL1Conflict {
+        nonterminal: Term,
+        lookahead: Term,
+        productions: Vec<Production>,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::EmptyGrammar => f.write_str("the grammar has no production"),
+            Error::UndefinedNonterminal(term) => {
+                f.write_fmt(format_args!("nonterminal {} is not defined", term))
+            }
+            Error::StartSymbolMissing(start) => {
+                f.write_fmt(format_args!("start symbol {} is not defined", start))
+            }
+            Error::Augmentation(e) => f.write_fmt(format_args!("{}", e)),
+            Error::UndefinedProduction { lhs, alternative } => {
+                f.write_fmt(format_args!("{} has no alternative {}", lhs, alternative))
+            }
+            Error::ReservedTerminal(term) => {
+                f.write_fmt(format_args!("terminal {} is reserved", term))
+            }
+            Error::MissingTransition { state, term } => f.write_fmt(format_args!(
+                "goto(I_{}, {}) is missing from the automaton",
+                state, term
+            )),
+            Error::LRConflict {
+                state,
+                lookahead,
+                kind,
+                actions,
+            } => f.write_fmt(format_args!(
+                "{} conflict at (I_{}, {}): [{}]",
+                kind,
+                state,
+                lookahead,
+                actions.iter().join(", ")
+            )),
+            Error::LL1Conflict {
+                nonterminal,
+                lookahead,
+                productions,
+            } => f.write_fmt(format_args!(
+                "LL(1) conflict at M[{}, {}]: [{}]",
+                nonterminal,
+                lookahead,
+                productions.iter().join(", ")
+            )),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Augmentation(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<AugmentationError> for Error {
+    fn from(e: AugmentationError) -> Self {
+        Error::Augmentation(e)
+    }
+}
+
+impl From<&Conflict> for Error {
+    fn from(conflict: &Conflict) -> Self {
+        Error::LRConflict {
+            state: conflict.state,
+            lookahead: conflict.lookahead.clone(),
+            kind: conflict.kind(),
+            actions: conflict.actions.clone(),
+        }
+    }
+}
+
+impl From<&LL1Conflict> for Error {
+    fn from(conflict: &LL1Conflict) -> Self {
+        Error::LL1Conflict {
+            nonterminal: conflict.nonterminal.clone(),
+            lookahead: conflict.lookahead.clone(),
+            productions: conflict
+                .productions
+                .iter()
+                .map(|(_, rhs)| {
+                    Production::from_parts(conflict.nonterminal.clone(), vec![rhs.clone()])
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::ir::grammar::InternedGrammar;
+    use crate::lalr::builder::LALRTableBuilder;
+    use crate::ll1::core::LL1Table;
+    use crate::lr0::core::LR0Closure;
+    use crate::lr0::table::LR0TableBuilder;
+    use crate::lr1::table::LR1TableBuilder;
+    use crate::slr::builder::SLRTableBuilder;
+    use crate::slr::conflict::ConflictKind;
+    use crate::utils::augmentation::AugmentationError;
+    use bnf::{Grammar, Production, Term};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <S'> ::= <S>
+        <S> ::= <L> '=' <R> | <R>
+        <L> ::= '*' <R> | 'id'
+        <R> ::= <L>
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn undefined_nonterminal() {
+        let grammar: Grammar = r#"
+        <S> ::= <A> 'a' | <B>
+        <A> ::= 'b'
+        "#
+        .parse()
+        .unwrap();
+        let error = InternedGrammar::try_new(&grammar).err().unwrap();
+        assert_eq!(
+            error,
+            Error::UndefinedNonterminal(Term::from_str("<B>").unwrap())
+        );
+        assert_eq!(error.to_string(), "nonterminal <B> is not defined");
+    }
+
+    #[test]
+    fn start_symbol_missing() {
+        let grammar = grammar();
+        let start = Term::from_str("<E>").unwrap();
+        assert_eq!(
+            LL1Table::try_new(&grammar, &start).err(),
+            Some(Error::StartSymbolMissing(start.clone()))
+        );
+    }
+
+    #[test]
+    fn augmentation() {
+        let grammar: Grammar = r#"
+        <S'> ::= <S> | 'a'
+        <S> ::= 'b'
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<S'> ::= <S> | 'a'").unwrap();
+        let error = LR0Closure::try_new(&grammar, &augmentation).err().unwrap();
+        assert_eq!(
+            error,
+            Error::Augmentation(AugmentationError::Malformed(augmentation.clone()))
+        );
+        assert!(std::error::Error::source(&error).is_some());
+        assert!(SLRTableBuilder::try_new(&grammar, &augmentation).is_err());
+        assert!(LALRTableBuilder::try_new(&grammar, &augmentation).is_err());
+        assert!(LR0TableBuilder::try_new(&grammar, &augmentation).is_err());
+        assert!(LR1TableBuilder::try_new(&grammar, &augmentation).is_err());
+    }
+
+    #[test]
+    fn conflict() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+
+        let slr = SLRTableBuilder::try_new(&grammar, &augmentation)
+            .unwrap()
+            .try_build()
+            .unwrap();
+        let error = slr.check_conflicts().err().unwrap();
+        assert!(matches!(
+            error,
+            Error::LRConflict {
+                kind: ConflictKind::ShiftReduce,
+                ..
+            }
+        ));
+        insta::assert_snapshot!(error);
+
+        let lr1 = LR1TableBuilder::try_new(&grammar, &augmentation)
+            .unwrap()
+            .try_build()
+            .unwrap();
+        assert_eq!(lr1.check_conflicts(), Ok(()));
+    }
+}
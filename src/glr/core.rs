@@ -0,0 +1,454 @@
+use crate::parser::core::{arity, SyntaxError, Token};
+use crate::parser::forest::{NodeId, ParseForest};
+use crate::parser::table::LRTable;
+use crate::parser::tree::ParseTree;
+use crate::slr::core::SLRInstruction;
+use crate::utils::dollar;
+use bnf::Term;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use log::debug;
+use std::collections::VecDeque;
+
+/// A Tomita-style generalized LR parser, which follows every action of a conflicted cell
+/// at once and merges the stacks into a graph-structured stack,
+/// works with every table implementing [`LRTable`]
+///
+/// An ε-reduction pops no edge, it links a node of the current level to another one,
+/// so every reduction of the level is run again when an edge is added below such a link
+pub struct Glr<'table, T> {
+    table: &'table T,
+}
+
+/// A node of the graph-structured stack, the state of the automaton
+/// after the first `level` tokens
+struct StackNode {
+    state: usize,
+    level: usize,
+    /// the nodes below, each with the forest node of the symbol in between
+    edges: Vec<(usize, NodeId)>,
+}
+
+/// A pending reduction by `production` from a stack node,
+/// restricted to the paths through its edge `first` if any
+type Reduction = (usize, usize, Option<usize>);
+
+impl<'table, T: LRTable> Glr<'table, T> {
+    pub fn new(table: &'table T) -> Glr<'table, T> {
+        Glr { table }
+    }
+
+    /// Parse the `tokens`, build the shared packed parse forest of every derivation
+    /// of the start symbol
+    pub fn parse<I>(&self, tokens: I) -> Result<ParseForest<'table>, SyntaxError<'table>>
+    where
+        I: IntoIterator,
+        I::Item: Token,
+    {
+        let tokens = tokens.into_iter().collect::<Vec<_>>();
+        let mut forest = ParseForest::new();
+        let mut stack = vec![StackNode {
+            state: 0,
+            level: 0,
+            edges: vec![],
+        }];
+        // the stack nodes of the current level by state
+        let mut frontier = IndexMap::from([(0, 0)]);
+
+        for position in 0..=tokens.len() {
+            let terminal = tokens
+                .get(position)
+                .map_or(dollar(), |token| token.terminal());
+
+            let mut queue = frontier
+                .values()
+                .flat_map(|node| self.reductions(&stack, *node, terminal, None))
+                .collect::<VecDeque<_>>();
+            while let Some((node, production, first)) = queue.pop_front() {
+                let (lhs, rhs) = self.table.production(production).unwrap();
+                for (below, children) in paths(&stack, node, arity(rhs), first) {
+                    let Some(goto) = self.table.goto(stack[below].state, lhs) else {
+                        continue;
+                    };
+                    debug!(
+                        "[GLR] I_{}: reduce by {} -> {}, goto I_{} from I_{}",
+                        stack[node].state, lhs, rhs, goto, stack[below].state
+                    );
+                    let (label, _) = forest.symbol(lhs, stack[below].level, position);
+                    forest.derive(label, lhs, rhs, production, &children);
+
+                    match frontier.get(&goto) {
+                        Some(&top) if stack[top].edges.contains(&(below, label)) => {}
+                        Some(&top) => {
+                            stack[top].edges.push((below, label));
+                            let edge = stack[top].edges.len() - 1;
+                            queue.extend(self.reductions(&stack, top, terminal, Some(edge)));
+                            // a node reaching `top` through ε-edges has new paths too
+                            let epsilon_edges = frontier.values().any(|node| {
+                                stack[*node]
+                                    .edges
+                                    .iter()
+                                    .any(|(below, _)| stack[*below].level == position)
+                            });
+                            if epsilon_edges {
+                                debug!("[GLR] new edge below an ε-edge, reduce the level again");
+                                for node in frontier.values().filter(|node| **node != top) {
+                                    queue.extend(self.reductions(&stack, *node, terminal, None));
+                                }
+                            }
+                        }
+                        None => {
+                            let top = stack.len();
+                            stack.push(StackNode {
+                                state: goto,
+                                level: position,
+                                edges: vec![(below, label)],
+                            });
+                            frontier.insert(goto, top);
+                            queue.extend(self.reductions(&stack, top, terminal, None));
+                        }
+                    }
+                }
+            }
+
+            let error = || SyntaxError {
+                state: frontier.keys().copied().min().unwrap_or_default(),
+                position,
+                unexpected: terminal.clone(),
+                expected: frontier
+                    .keys()
+                    .flat_map(|state| self.table.expected(*state))
+                    .unique()
+                    .sorted()
+                    .collect(),
+            };
+
+            let Some(token) = tokens.get(position) else {
+                // <S'> -> <S> • is only reached from I_0, on the edge labelled by <S>
+                let root = frontier
+                    .values()
+                    .filter(|node| {
+                        self.table
+                            .actions(stack[**node].state, terminal)
+                            .contains(&SLRInstruction::Accept)
+                    })
+                    .flat_map(|node| stack[*node].edges.iter())
+                    .find(|(below, _)| *below == 0)
+                    .map(|(_, label)| *label)
+                    .ok_or_else(error)?;
+                debug!("[GLR] accept");
+                forest.root = Some(root);
+                return Ok(forest);
+            };
+
+            let mut next = IndexMap::new();
+            for (&state, &node) in frontier.iter() {
+                for action in self.table.actions(state, terminal) {
+                    let SLRInstruction::Shift(to) = *action else {
+                        continue;
+                    };
+                    debug!("[GLR] I_{}: shift {}, goto I_{}", state, terminal, to);
+                    let leaf = forest.leaf(position, token);
+                    let top = *next.entry(to).or_insert_with(|| {
+                        stack.push(StackNode {
+                            state: to,
+                            level: position + 1,
+                            edges: vec![],
+                        });
+                        stack.len() - 1
+                    });
+                    stack[top].edges.push((node, leaf));
+                }
+            }
+            if next.is_empty() {
+                return Err(error());
+            }
+            frontier = next;
+        }
+        unreachable!("the end of the input either accepts or fails")
+    }
+
+    /// Parse the `tokens`, build the first parse tree of the start symbol
+    pub fn parse_tree<I>(&self, tokens: I) -> Result<ParseTree<'table>, SyntaxError<'table>>
+    where
+        I: IntoIterator,
+        I::Item: Token,
+    {
+        Ok(self.parse(tokens)?.tree().unwrap())
+    }
+
+    /// Whether the `tokens` can be parsed
+    pub fn recognize<I>(&self, tokens: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Token,
+    {
+        self.parse(tokens).is_ok()
+    }
+
+    /// Every reduction of the stack node `node` on the lookahead `terminal`
+    fn reductions(
+        &self,
+        stack: &[StackNode],
+        node: usize,
+        terminal: &Term,
+        first: Option<usize>,
+    ) -> Vec<Reduction> {
+        self.table
+            .actions(stack[node].state, terminal)
+            .iter()
+            .filter_map(|action| match action {
+                SLRInstruction::Reduce(production) => Some((node, *production, first)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Every path of `length` edges down from `node`, starting with the edge `first` if any,
+/// as the node at the end and the labels along the path from left to right
+fn paths(
+    stack: &[StackNode],
+    node: usize,
+    length: usize,
+    first: Option<usize>,
+) -> Vec<(usize, Vec<NodeId>)> {
+    if length == 0 && first.is_some() {
+        // the empty path of an ε-reduction goes through no edge
+        return vec![];
+    }
+    let mut paths = vec![(node, vec![])];
+    for step in 0..length {
+        paths = paths
+            .into_iter()
+            .flat_map(|(node, labels)| {
+                let edges = match (step, first) {
+                    (0, Some(edge)) => &stack[node].edges[edge..=edge],
+                    _ => &stack[node].edges[..],
+                };
+                edges.iter().map(move |(below, label)| {
+                    let mut labels = labels.clone();
+                    labels.push(*label);
+                    (*below, labels)
+                })
+            })
+            .collect();
+    }
+    paths
+        .into_iter()
+        .map(|(node, mut labels)| {
+            labels.reverse();
+            (node, labels)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::earley::core::Earley;
+    use crate::glr::core::Glr;
+    use crate::lalr::builder::LALRTableBuilder;
+    use crate::lr0::table::LR0TableBuilder;
+    use crate::lr1::table::LR1TableBuilder;
+    use crate::parser::core::{tokens, Lexeme, Parser};
+    use crate::slr::builder::SLRTableBuilder;
+    use crate::utils::dollar;
+    use bnf::{Grammar, Production, Term};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <E'> ::= <E>
+        <E> ::= <E> '+' <E> | <E> '*' <E> | '(' <E> ')' | 'id'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn it_works() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        assert!(slr.has_conflicts());
+        let glr = Glr::new(&slr);
+
+        let forest = glr.parse(Lexeme::split_whitespace("id + id * id")).unwrap();
+        assert!(forest.is_ambiguous());
+        insta::assert_snapshot!(forest);
+        // (id + id) * id and id + (id * id)
+        let root = forest.node(forest.root().unwrap());
+        assert_eq!(root.packed().len(), 2);
+        assert_eq!(root.range(), Some(0..5));
+
+        let forest = glr.parse(tokens("( id )")).unwrap();
+        assert!(!forest.is_ambiguous());
+        assert_eq!(forest.tree().unwrap().text(), "( id )");
+
+        let error = glr.parse(tokens("id + * id")).unwrap_err();
+        assert_eq!(error.position(), 2);
+        assert_eq!(error.unexpected(), &Term::from_str("'*'").unwrap());
+        let error = glr.parse(tokens("id +")).unwrap_err();
+        assert_eq!(error.position(), 2);
+        assert_eq!(error.unexpected(), dollar());
+        assert!(!glr.recognize(tokens("")));
+    }
+
+    #[test]
+    fn tables() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let lr0 = LR0TableBuilder::new(&grammar, &augmentation).build();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let lalr = LALRTableBuilder::new(&grammar, &augmentation).build();
+        let lr1 = LR1TableBuilder::new(&grammar, &augmentation).build();
+        let earley = Earley::new(&grammar);
+
+        for input in [
+            "id",
+            "id + id * id",
+            "id + id + id + id",
+            "( id + id ) * id",
+            "id * ( id",
+            "id id",
+        ] {
+            let expected = earley.parse(tokens(input)).is_member();
+            assert_eq!(Glr::new(&lr0).recognize(tokens(input)), expected);
+            assert_eq!(Glr::new(&slr).recognize(tokens(input)), expected);
+            assert_eq!(Glr::new(&lalr).recognize(tokens(input)), expected);
+            assert_eq!(Glr::new(&lr1).recognize(tokens(input)), expected);
+        }
+    }
+
+    #[test]
+    fn non_deterministic() {
+        // unambiguous, but the middle of a palindrome can not be found with any lookahead
+        let grammar: Grammar = r#"
+        <S'> ::= <S>
+        <S> ::= 'a' <S> 'a' | 'b' <S> 'b' | 'a' | 'b'
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+        let lr1 = LR1TableBuilder::new(&grammar, &augmentation).build();
+        assert!(lr1.has_conflicts());
+        let glr = Glr::new(&lr1);
+
+        assert!(Parser::new(&lr1).parse(tokens("a b a")).is_err());
+        let forest = glr.parse(tokens("a b a")).unwrap();
+        assert!(!forest.is_ambiguous());
+        assert_eq!(forest.tree().unwrap().text(), "a b a");
+        assert!(glr.recognize(tokens("a b b a b b a")));
+        assert!(!glr.recognize(tokens("a b")));
+    }
+
+    #[test]
+    fn oracle() {
+        let grammar: Grammar = r#"
+        <E'> ::= <E>
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= <T> '*' <F> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let parser = Parser::new(&slr);
+        let glr = Glr::new(&slr);
+
+        for input in [
+            "id",
+            "id + id * id",
+            "( id + id ) * id",
+            "id * ( id",
+            "id id",
+            "( ( id ) )",
+        ] {
+            match parser.parse_tree(tokens(input)) {
+                Ok(expected) => assert_eq!(glr.parse_tree(tokens(input)).unwrap(), expected),
+                Err(error) => assert_eq!(
+                    glr.parse(tokens(input)).unwrap_err().position(),
+                    error.position()
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn epsilon_production() {
+        let grammar: Grammar = r#"
+        <S'> ::= <S>
+        <S> ::= 'a' <S> | 'ε'
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let glr = Glr::new(&slr);
+        let earley = Earley::new(&grammar);
+
+        for input in ["", "a", "a a a", "b", "a b"] {
+            let chart = earley.parse(tokens(input));
+            match glr.parse_tree(tokens(input)) {
+                // the augmentation <S'> -> <S> is never reduced by the LR parser
+                Ok(tree) => assert_eq!(chart.parse_tree().unwrap().children(), [tree]),
+                Err(_) => assert!(!chart.is_member()),
+            }
+        }
+        assert_eq!(glr.parse(tokens("a a")).unwrap().count(), Some(1));
+    }
+
+    #[test]
+    fn hidden_left_recursion() {
+        // <S> is left recursive behind the nullable <A>, and every cell of I_0 conflicts
+        let grammar: Grammar = r#"
+        <S'> ::= <S>
+        <S> ::= <A> <S> 'b' | 'x'
+        <A> ::= 'ε'
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+        let lr0 = LR0TableBuilder::new(&grammar, &augmentation).build();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let earley = Earley::new(&grammar);
+
+        for input in ["x", "x b", "x b b b", "b", "x x", ""] {
+            let expected = earley.parse(tokens(input)).is_member();
+            assert_eq!(Glr::new(&lr0).recognize(tokens(input)), expected);
+            assert_eq!(Glr::new(&slr).recognize(tokens(input)), expected);
+        }
+        let forest = Glr::new(&slr).parse(tokens("x b b")).unwrap();
+        assert_eq!(forest.count(), Some(1));
+        assert_eq!(forest.tree().unwrap().text(), "x b b");
+    }
+
+    #[test]
+    fn epsilon_edges() {
+        // the ε-edges of <A> and <B> are laid before the edges below them are complete
+        let grammar: Grammar = r#"
+        <S'> ::= <S>
+        <S> ::= <A> <B>
+        <A> ::= 'ε'
+        <B> ::= <A> | 'ε' | 'a' <S> <S>
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+        let lr0 = LR0TableBuilder::new(&grammar, &augmentation).build();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let lalr = LALRTableBuilder::new(&grammar, &augmentation).build();
+        let earley = Earley::new(&grammar);
+
+        for input in ["", "a", "a a", "a a a", "b"] {
+            let chart = earley.parse(tokens(input));
+            for table in [&lr0, &slr, &lalr] {
+                match Glr::new(table).parse(tokens(input)) {
+                    Ok(forest) => assert_eq!(forest.count(), chart.forest().count()),
+                    Err(_) => assert!(!chart.is_member()),
+                }
+            }
+        }
+    }
+}
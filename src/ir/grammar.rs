@@ -0,0 +1,341 @@
+use crate::error::Result;
+use crate::ir::symbol::{SymbolId, SymbolTable};
+use crate::utils::{check_grammar, dollar, epsilon};
+use bnf::{Expression, Grammar, Production, Term};
+use itertools::Itertools;
+use std::fmt::{Display, Formatter};
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// A dense id of a production, in the order of `Grammar::productions_iter`
+/// and then `Production::rhs_iter`
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct ProductionId(pub(crate) u32);
+
+impl ProductionId {
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Display for ProductionId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("r{}", self.0))
+    }
+}
+
+/// A single alternative `lhs -> rhs`, an empty `rhs` is ε
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct InternedProduction {
+    pub(crate) lhs: SymbolId,
+    pub(crate) rhs: Vec<SymbolId>,
+    /// the original rhs, 'ε' included, which the parse trees refer to
+    pub(crate) expression: Expression,
+    /// the index of the `bnf::Production` it comes from
+    pub(crate) line: usize,
+}
+
+impl InternedProduction {
+    pub fn lhs(&self) -> SymbolId {
+        self.lhs
+    }
+
+    pub fn rhs(&self) -> &[SymbolId] {
+        &self.rhs
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    pub fn is_epsilon(&self) -> bool {
+        self.rhs.is_empty()
+    }
+}
+
+/// An owned grammar whose symbols and productions are dense integers,
+/// nothing built on it borrows the `bnf::Grammar` it is converted from
+///
+/// `$` is always interned first, then every lhs in the order they are defined,
+/// `'ε'` is not interned at all: an alternative made of `'ε'` only is an empty rhs,
+/// a terminal `'$'` of the grammar would be taken for the end of input
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InternedGrammar {
+    pub(crate) symbols: SymbolTable,
+    pub(crate) productions: Vec<InternedProduction>,
+}
+
+impl InternedGrammar {
+    pub fn new(grammar: &Grammar) -> InternedGrammar {
+        let mut symbols = SymbolTable::new();
+        symbols.intern(dollar());
+        for production in grammar.productions_iter() {
+            symbols.intern(&production.lhs);
+        }
+
+        let mut interned = InternedGrammar {
+            symbols,
+            productions: vec![],
+        };
+        for (line, production) in grammar.productions_iter().enumerate() {
+            for expr in production.rhs_iter() {
+                interned.push(&production.lhs, expr, line);
+            }
+        }
+        interned
+    }
+
+    /// Same as [`InternedGrammar::new`], but fail if the grammar has no production,
+    /// or uses a nonterminal without defining it
+    pub fn try_new(grammar: &Grammar) -> Result<InternedGrammar> {
+        check_grammar(grammar)?;
+        Ok(InternedGrammar::new(grammar))
+    }
+
+    fn push(&mut self, lhs: &Term, expression: &Expression, line: usize) -> ProductionId {
+        let lhs = self.symbols.intern(lhs);
+        let rhs = expression
+            .terms_iter()
+            .filter(|term| *term != epsilon())
+            .map(|term| self.symbols.intern(term))
+            .collect();
+        self.productions.push(InternedProduction {
+            lhs,
+            rhs,
+            expression: expression.clone(),
+            line,
+        });
+        ProductionId(self.productions.len() as u32 - 1)
+    }
+
+    /// The id of `production`, which must have a single alternative,
+    /// appended as a production of its own if the grammar lacks it
+    pub(crate) fn find_or_insert(&mut self, production: &Production) -> Option<ProductionId> {
+        let rhs = production.rhs_iter().exactly_one().ok()?;
+        if let Some(id) = self.find(&production.lhs, rhs) {
+            return Some(id);
+        }
+        let line = self.productions.last().map_or(0, |last| last.line + 1);
+        Some(self.push(&production.lhs, rhs, line))
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    pub fn term(&self, id: SymbolId) -> &Term {
+        self.symbols.term(id)
+    }
+
+    pub fn id(&self, term: &Term) -> Option<SymbolId> {
+        self.symbols.id(term)
+    }
+
+    /// The end of input `$`
+    pub fn end(&self) -> SymbolId {
+        SymbolId(0)
+    }
+
+    /// The lhs of the first production
+    pub fn start(&self) -> Option<SymbolId> {
+        self.productions.first().map(|production| production.lhs)
+    }
+
+    pub fn terminals(&self) -> impl Iterator<Item = SymbolId> + '_ {
+        self.symbols
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| *id != self.end() && self.symbols.is_terminal(*id))
+    }
+
+    pub fn nonterminals(&self) -> impl Iterator<Item = SymbolId> + '_ {
+        self.symbols
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| self.symbols.is_nonterminal(*id))
+    }
+
+    pub fn production(&self, id: ProductionId) -> &InternedProduction {
+        &self.productions[id.index()]
+    }
+
+    pub fn productions(&self) -> impl Iterator<Item = (ProductionId, &InternedProduction)> {
+        self.productions
+            .iter()
+            .enumerate()
+            .map(|(i, production)| (ProductionId(i as u32), production))
+    }
+
+    /// All the alternatives of `lhs`
+    pub fn productions_of(
+        &self,
+        lhs: SymbolId,
+    ) -> impl Iterator<Item = (ProductionId, &InternedProduction)> {
+        self.productions()
+            .filter(move |(_, production)| production.lhs == lhs)
+    }
+
+    /// The `alternative`-th alternative of `lhs`
+    pub fn alternative(&self, lhs: &Term, alternative: usize) -> Option<ProductionId> {
+        self.productions_of(self.id(lhs)?)
+            .nth(alternative)
+            .map(|(id, _)| id)
+    }
+
+    /// The index of the production `id` among all the alternatives of its lhs
+    pub fn alternative_of(&self, id: ProductionId) -> usize {
+        self.productions_of(self.production(id).lhs)
+            .position(|(other, _)| other == id)
+            .unwrap()
+    }
+
+    /// The production `id` as `(lhs, rhs)` with the original terms
+    pub fn rule(&self, id: ProductionId) -> (&Term, &Expression) {
+        let production = self.production(id);
+        (self.term(production.lhs), &production.expression)
+    }
+
+    pub fn len(&self) -> usize {
+        self.productions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.productions.is_empty()
+    }
+
+    /// The id of the production `lhs -> rhs` of the original grammar
+    pub fn find(&self, lhs: &Term, rhs: &Expression) -> Option<ProductionId> {
+        let lhs = self.id(lhs)?;
+        let rhs = rhs
+            .terms_iter()
+            .filter(|term| *term != epsilon())
+            .map(|term| self.id(term))
+            .collect::<Option<Vec<_>>>()?;
+        self.productions_of(lhs)
+            .find(|(_, production)| production.rhs == rhs)
+            .map(|(id, _)| id)
+    }
+
+    /// `lhs -> rhs` with the original terms, ε for an empty rhs
+    pub fn display(&self, id: ProductionId) -> String {
+        let production = self.production(id);
+        format!(
+            "{} -> {}",
+            self.term(production.lhs),
+            self.display_rhs(&production.rhs)
+        )
+    }
+
+    fn display_rhs(&self, rhs: &[SymbolId]) -> String {
+        match rhs.is_empty() {
+            true => epsilon().to_string(),
+            false => rhs.iter().map(|id| self.term(*id)).join(" "),
+        }
+    }
+
+    /// Convert back to a `bnf::Grammar`, alternatives are grouped
+    /// into the productions they come from
+    pub fn to_grammar(&self) -> Grammar {
+        Grammar::from_parts(
+            self.productions
+                .iter()
+                .chunk_by(|production| production.line)
+                .into_iter()
+                .map(|(_, mut alternatives)| {
+                    let first = alternatives.next().unwrap();
+                    let lhs = self.term(first.lhs).clone();
+                    let rhs = std::iter::once(first)
+                        .chain(alternatives)
+                        .map(|production| production.expression.clone())
+                        .collect();
+                    Production::from_parts(lhs, rhs)
+                })
+                .collect(),
+        )
+    }
+
+    pub fn grammar_table(&self) -> Table {
+        let mut builder = Builder::default();
+        builder.push_record(["Rule"]);
+        for (id, _) in self.productions() {
+            builder.push_record([self.display(id)]);
+        }
+        builder.index().build()
+    }
+}
+
+impl From<&Grammar> for InternedGrammar {
+    fn from(grammar: &Grammar) -> Self {
+        InternedGrammar::new(grammar)
+    }
+}
+
+impl Display for InternedGrammar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}", self.to_grammar()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ir::grammar::{InternedGrammar, ProductionId};
+    use bnf::{Expression, Grammar, Term};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <E> ::= <T> <E'>
+        <E'> ::= '+' <T> <E'> | 'ε'
+        <T> ::= <F> <T'>
+        <T'> ::= '*' <F> <T'> | 'ε'
+        <F> ::= '(' <E> ')' | 'id'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn round_trip() {
+        let grammar = grammar();
+        let interned = InternedGrammar::from(&grammar);
+        assert_eq!(interned.to_grammar(), grammar);
+
+        assert_eq!(interned.len(), 8);
+        assert_eq!(interned.symbols().len(), 11);
+        assert_eq!(interned.terminals().count(), 5);
+        assert_eq!(interned.nonterminals().count(), 5);
+        assert_eq!(
+            interned.start(),
+            interned.id(&Term::from_str("<E>").unwrap())
+        );
+        assert!(interned.production(ProductionId(2)).is_epsilon());
+        insta::assert_snapshot!(interned.grammar_table());
+    }
+
+    #[test]
+    fn find() {
+        let grammar = grammar();
+        let interned = InternedGrammar::from(&grammar);
+
+        let lhs = Term::from_str("<T'>").unwrap();
+        let epsilon = Expression::from_str("'ε'").unwrap();
+        assert_eq!(interned.find(&lhs, &epsilon), Some(ProductionId(5)));
+
+        assert_eq!(interned.alternative(&lhs, 1), Some(ProductionId(5)));
+        assert_eq!(interned.alternative_of(ProductionId(5)), 1);
+
+        let lhs = Term::from_str("<T>").unwrap();
+        assert_eq!(interned.find(&lhs, &epsilon), None);
+        assert_eq!(interned.alternative(&lhs, 1), None);
+    }
+
+    #[test]
+    fn owned() {
+        fn assert_owned<T: Send + Sync + 'static>(_: &T) {}
+
+        let interned = InternedGrammar::from(&grammar());
+        assert_owned(&interned);
+        let handle = std::thread::spawn(move || interned.len());
+        assert_eq!(handle.join().unwrap(), 8);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod grammar;
+pub mod symbol;
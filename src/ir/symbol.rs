@@ -0,0 +1,75 @@
+use bnf::Term;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// A dense id of a terminal or nonterminal in a [`SymbolTable`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct SymbolId(pub(crate) u32);
+
+impl SymbolId {
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Display for SymbolId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("#{}", self.0))
+    }
+}
+
+/// Interns every `Term` once, in the order of first appearance
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SymbolTable {
+    pub(crate) terms: Vec<Term>,
+    pub(crate) ids: HashMap<Term, SymbolId>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable::default()
+    }
+
+    /// The id of `term`, interning it if it is new
+    pub fn intern(&mut self, term: &Term) -> SymbolId {
+        if let Some(id) = self.ids.get(term) {
+            return *id;
+        }
+        let id = SymbolId(self.terms.len() as u32);
+        self.terms.push(term.clone());
+        self.ids.insert(term.clone(), id);
+        id
+    }
+
+    pub fn id(&self, term: &Term) -> Option<SymbolId> {
+        self.ids.get(term).copied()
+    }
+
+    pub fn term(&self, id: SymbolId) -> &Term {
+        &self.terms[id.index()]
+    }
+
+    pub fn is_terminal(&self, id: SymbolId) -> bool {
+        matches!(self.term(id), Term::Terminal(_))
+    }
+
+    pub fn is_nonterminal(&self, id: SymbolId) -> bool {
+        matches!(self.term(id), Term::Nonterminal(_))
+    }
+
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Iterate all the symbols in the order of their ids
+    pub fn iter(&self) -> impl Iterator<Item = (SymbolId, &Term)> {
+        self.terms
+            .iter()
+            .enumerate()
+            .map(|(i, term)| (SymbolId(i as u32), term))
+    }
+}
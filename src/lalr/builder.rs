@@ -0,0 +1,164 @@
+use crate::error::Result;
+use crate::lalr::lookahead::LALRLookahead;
+use crate::lr0::core::LR0Closure;
+use crate::slr::conflict::ActionCandidates;
+use crate::slr::core::SLRTable;
+use crate::slr::precedence::Precedence;
+use bnf::{Grammar, Production};
+use log::info;
+
+/// Builds an LALR(1) table with exactly as many states as the `LR0Closure`,
+/// the result shares `SLRTable` so that both can be compared directly
+pub struct LALRTableBuilder {
+    lookahead: LALRLookahead,
+    closure: LR0Closure,
+    precedence: Precedence,
+}
+
+impl LALRTableBuilder {
+    /// `augmentation` `<S'> ::= <S>` must be the only production of `<S'>` in `grammar`,
+    /// [`augment`](crate::utils::augmentation::augment) synthesizes one
+    ///
+    /// Panics if the grammar or the augmentation is invalid, see [`LALRTableBuilder::try_new`]
+    pub fn new(grammar: &Grammar, augmentation: &Production) -> LALRTableBuilder {
+        Self::try_new(grammar, augmentation).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as [`LALRTableBuilder::new`], but fail if the grammar or the augmentation is invalid
+    pub fn try_new(grammar: &Grammar, augmentation: &Production) -> Result<LALRTableBuilder> {
+        // validates the grammar and the augmentation
+        let closure = LR0Closure::try_new(grammar, augmentation)?;
+        let lookahead = LALRLookahead::new(&closure);
+        info!("LR0 Closure:\n{}", closure.closure_table());
+        info!("LR0 Transition Table:\n{}", closure.transition_table());
+        info!("LALR Follow:\n{}", lookahead.tabled(&closure.grammar));
+        Ok(LALRTableBuilder {
+            lookahead,
+            closure,
+            precedence: Precedence::new(),
+        })
+    }
+
+    /// Resolve the shift/reduce conflicts by the precedence declarations
+    pub fn precedence(mut self, precedence: Precedence) -> LALRTableBuilder {
+        self.precedence = precedence;
+        self
+    }
+
+    pub fn build(self) -> SLRTable {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as [`LALRTableBuilder::build`], but fail if a transition is missing from the automaton,
+    /// or a `%prec` names a production out of the grammar
+    pub fn try_build(self) -> Result<SLRTable> {
+        let grammar = &self.closure.grammar;
+        let mut actions = ActionCandidates::new();
+        actions.fill(
+            self.closure.enumerate_lr0(),
+            |from, via| self.closure.transition(from, via),
+            self.closure.augmentation,
+            grammar,
+            |state, production, _| {
+                self.lookahead
+                    .lookahead(state, production.index())
+                    .collect()
+            },
+        )?;
+        let (table, conflicts) = actions.resolve(self.closure.len(), grammar, &self.precedence)?;
+        Ok(SLRTable {
+            grammar: self.closure.grammar,
+            table,
+            conflicts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lalr::builder::LALRTableBuilder;
+    use crate::lr0::core::LR0Closure;
+    use crate::lr1::table::LR1TableBuilder;
+    use crate::slr::builder::SLRTableBuilder;
+    use crate::slr::conflict::ConflictKind;
+    use crate::slr::core::SLRInstruction;
+    use bnf::Production;
+    use std::str::FromStr;
+
+    #[test]
+    fn same_as_slr() {
+        let grammar = r#"
+        <E'> ::= <E>
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= <T> '*' <F> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let lalr = LALRTableBuilder::new(&grammar, &augmentation).build();
+        assert!(!lalr.has_conflicts());
+        assert_eq!(
+            slr.parsing_table().to_string(),
+            lalr.parsing_table().to_string()
+        );
+    }
+
+    #[test]
+    fn lalr1_but_not_slr1() {
+        let grammar = r#"
+        <S'> ::= <S>
+        <S> ::= <L> '=' <R> | <R>
+        <L> ::= '*' <R> | 'id'
+        <R> ::= <L>
+        "#
+        .parse()
+        .unwrap();
+
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        assert!(slr.has_conflicts());
+
+        let lalr = LALRTableBuilder::new(&grammar, &augmentation).build();
+        assert!(!lalr.has_conflicts());
+        assert_eq!(
+            lalr.table.len(),
+            LR0Closure::new(&grammar, &augmentation).len()
+        );
+        insta::assert_snapshot!(lalr);
+    }
+
+    #[test]
+    fn lr1_but_not_lalr1() {
+        let grammar = r#"
+        <S'> ::= <S>
+        <S> ::= 'a' <A> 'd' | 'b' <B> 'd' | 'a' <B> 'e' | 'b' <A> 'e'
+        <A> ::= 'c'
+        <B> ::= 'c'
+        "#
+        .parse()
+        .unwrap();
+
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+
+        let lr1 = LR1TableBuilder::new(&grammar, &augmentation).build();
+        assert!(!lr1.has_conflicts());
+
+        // merging the states of [<A> -> 'c' •] and [<B> -> 'c' •]
+        // mixes the lookaheads of both productions
+        let lalr = LALRTableBuilder::new(&grammar, &augmentation).build();
+        assert_eq!(lalr.conflicts().len(), 2);
+        for conflict in lalr.conflicts() {
+            assert_eq!(conflict.kind(), ConflictKind::ReduceReduce);
+            assert_eq!(
+                conflict.actions(),
+                &[SLRInstruction::Reduce(5), SLRInstruction::Reduce(6)]
+            );
+        }
+        insta::assert_snapshot!(lalr.conflict_table());
+    }
+}
@@ -0,0 +1,225 @@
+use crate::ir::grammar::InternedGrammar;
+use crate::ir::symbol::SymbolId;
+use crate::lr0::core::LR0Closure;
+use crate::utils::first::First;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use log::debug;
+use std::collections::{BTreeSet, HashMap};
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// A nonterminal transition (p, A) of the LR(0) automaton
+type NonterminalTransition = (usize, SymbolId);
+
+/// LALR(1) lookaheads computed by the DeRemer–Pennello relations
+/// (reads, includes and lookback) over the LR(0) automaton
+pub struct LALRLookahead {
+    /// LA(q, A -> ω), by q and the index of A -> ω
+    lookaheads: HashMap<(usize, usize), BTreeSet<SymbolId>>,
+    /// Follow(p, A) of every nonterminal transition
+    follow: IndexMap<NonterminalTransition, BTreeSet<SymbolId>>,
+}
+
+impl LALRLookahead {
+    pub fn new(closure: &LR0Closure) -> LALRLookahead {
+        let grammar = &closure.grammar;
+        let symbols = grammar.symbols();
+        let first = First::new(grammar);
+        let augmentation = grammar.production(closure.augmentation).lhs;
+
+        // every nonterminal transition, plus the virtual transition (0, S')
+        // which is the only one followed by $
+        let transitions = closure
+            .transitions
+            .keys()
+            .filter(|(_, id)| symbols.is_nonterminal(*id))
+            .copied()
+            .sorted_by_key(|(p, id)| (*p, grammar.term(*id)))
+            .chain(std::iter::once((0, augmentation)))
+            .collect::<Vec<_>>();
+        let index = transitions
+            .iter()
+            .enumerate()
+            .map(|(i, transition)| (*transition, i))
+            .collect::<HashMap<_, _>>();
+
+        // DR(p, A) = { t | p --A--> r --t--> }
+        // (p, A) reads (r, C) iff p --A--> r --C--> and C is nullable
+        let mut direct_reads = vec![BTreeSet::new(); transitions.len()];
+        let mut reads = vec![Vec::new(); transitions.len()];
+        for (i, (p, a)) in transitions.iter().enumerate() {
+            let Some(r) = closure.transition(*p, *a) else {
+                // the virtual transition (0, S')
+                direct_reads[i].insert(grammar.end());
+                continue;
+            };
+            for (_, t) in closure.transitions.keys().filter(|(from, _)| *from == r) {
+                if symbols.is_terminal(*t) {
+                    direct_reads[i].insert(*t);
+                } else if first.is_nullable(*t) {
+                    reads[i].push(index[&(r, *t)]);
+                }
+            }
+        }
+        let read = digraph(&reads, direct_reads);
+
+        // (p, A) includes (p', B) iff B -> β A γ, γ is nullable and p' --β--> p
+        // (q, A -> ω) lookback (p, A) iff p --ω--> q
+        let mut includes = vec![Vec::new(); transitions.len()];
+        let mut lookback = HashMap::new();
+        for (i, (p, b)) in transitions.iter().enumerate() {
+            // the augmentation is a production of the grammar too,
+            // and A -> 'ε' has an empty rhs, it is reduced where it is predicted
+            for (production, rule) in grammar.productions_of(*b) {
+                let terms = &rule.rhs;
+                let mut state = Some(*p);
+                for (j, term) in terms.iter().enumerate() {
+                    let Some(from) = state else { break };
+                    if symbols.is_nonterminal(*term)
+                        && first.is_nullable_seq(terms[j + 1..].iter().copied())
+                    {
+                        if let Some(k) = index.get(&(from, *term)) {
+                            includes[*k].push(i);
+                        }
+                    }
+                    state = closure.transition(from, *term);
+                }
+                if let Some(q) = state {
+                    lookback
+                        .entry((q, production.index()))
+                        .or_insert_with(Vec::new)
+                        .push(i);
+                }
+            }
+        }
+        let follow = digraph(&includes, read);
+
+        let lookaheads = lookback
+            .into_iter()
+            .map(|(key, transitions)| {
+                let la = transitions
+                    .iter()
+                    .flat_map(|i| follow[*i].iter().copied())
+                    .collect::<BTreeSet<_>>();
+                debug!(
+                    "[LALR Lookahead] LA(I_{}, r{}) = {{{}}}",
+                    key.0,
+                    key.1,
+                    la.iter().map(|t| grammar.term(*t)).sorted().join(", ")
+                );
+                (key, la)
+            })
+            .collect();
+
+        let follow = transitions.into_iter().zip(follow).collect();
+
+        LALRLookahead { lookaheads, follow }
+    }
+
+    /// LA(q, A -> ω), the terminals on which A -> ω, the production `index`,
+    /// is reduced in state q
+    pub fn lookahead(&self, state: usize, index: usize) -> impl Iterator<Item = SymbolId> + '_ {
+        self.lookaheads
+            .get(&(state, index))
+            .into_iter()
+            .flat_map(|set| set.iter().copied())
+    }
+
+    /// Follow(p, A) of every nonterminal transition, with the terms of `grammar`
+    pub fn tabled(&self, grammar: &InternedGrammar) -> Table {
+        let mut table = Builder::new();
+        table.push_record(["Transition", "Follow(p, A)"]);
+        for ((p, a), follow) in self.follow.iter() {
+            table.push_record([
+                format!("(I_{}, {})", p, grammar.term(*a)),
+                follow.iter().map(|t| grammar.term(*t)).sorted().join(", "),
+            ]);
+        }
+        table.build()
+    }
+}
+
+/// The Digraph algorithm of DeRemer and Pennello,
+/// computes the smallest F with F(x) = F'(x) ∪ ⋃ { F(y) | x R y }
+fn digraph(relation: &[Vec<usize>], init: Vec<BTreeSet<SymbolId>>) -> Vec<BTreeSet<SymbolId>> {
+    struct Digraph<'a> {
+        relation: &'a [Vec<usize>],
+        stack: Vec<usize>,
+        depth: Vec<usize>,
+        f: Vec<BTreeSet<SymbolId>>,
+    }
+
+    impl<'a> Digraph<'a> {
+        fn traverse(&mut self, x: usize) {
+            self.stack.push(x);
+            let d = self.stack.len();
+            self.depth[x] = d;
+            for &y in self.relation[x].iter() {
+                if self.depth[y] == 0 {
+                    self.traverse(y);
+                }
+                self.depth[x] = self.depth[x].min(self.depth[y]);
+                let f_y = self.f[y].clone();
+                self.f[x].extend(f_y);
+            }
+            if self.depth[x] == d {
+                // x is the root of a strongly connected component,
+                // every member shares the same set
+                while let Some(top) = self.stack.pop() {
+                    self.depth[top] = usize::MAX;
+                    if top == x {
+                        break;
+                    }
+                    self.f[top] = self.f[x].clone();
+                }
+            }
+        }
+    }
+
+    let mut digraph = Digraph {
+        relation,
+        stack: Vec::new(),
+        depth: vec![0; init.len()],
+        f: init,
+    };
+    for x in 0..relation.len() {
+        if digraph.depth[x] == 0 {
+            digraph.traverse(x);
+        }
+    }
+    digraph.f
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lalr::lookahead::LALRLookahead;
+    use crate::lr0::core::LR0Closure;
+    use bnf::Production;
+    use itertools::Itertools;
+    use std::str::FromStr;
+
+    #[test]
+    fn it_works() {
+        let grammar = r#"
+        <S'> ::= <S>
+        <S> ::= <L> '=' <R> | <R>
+        <L> ::= '*' <R> | 'id'
+        <R> ::= <L>
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+        let closure = LR0Closure::new(&grammar, &augmentation);
+        let lookahead = LALRLookahead::new(&closure);
+
+        // I_3 = [<R> -> <L> •, <S> -> <L> • '=' <R>], only reduce <R> -> <L>, r5, on $
+        let la = lookahead
+            .lookahead(3, 5)
+            .map(|t| closure.grammar().term(t).to_string())
+            .collect_vec();
+        assert_eq!(la, vec!["'$'"]);
+
+        insta::assert_snapshot!(lookahead.tabled(closure.grammar()));
+    }
+}
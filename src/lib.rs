@@ -1,3 +1,13 @@
+pub mod cyk;
+pub mod earley;
+pub mod error;
+pub mod glr;
+pub mod ir;
+pub mod lalr;
+pub mod ll1;
 pub mod lr0;
+pub mod lr1;
+pub mod parser;
 pub mod slr;
+pub mod transform;
 pub mod utils;
@@ -0,0 +1,107 @@
+use crate::error::Result;
+use crate::ir::grammar::{InternedGrammar, ProductionId};
+use crate::ir::symbol::SymbolId;
+use crate::ll1::core::{LL1Conflict, LL1Table};
+use crate::utils::first::First;
+use crate::utils::follow::Follow;
+use crate::utils::table::Table as LookupTable;
+use crate::utils::{check_end, check_start};
+use bnf::{Grammar, Term};
+use itertools::Itertools;
+use log::{debug, warn};
+
+pub struct LL1TableBuilder {
+    grammar: InternedGrammar,
+    first: First,
+    follow: Follow,
+    table: LookupTable<SymbolId, SymbolId, Vec<ProductionId>>,
+}
+
+impl LL1TableBuilder {
+    pub(crate) fn new(grammar: &Grammar, start: &Term) -> LL1TableBuilder {
+        let grammar = InternedGrammar::new(grammar);
+        let first = First::new(&grammar);
+        let start = grammar
+            .id(start)
+            .unwrap_or_else(|| panic!("start symbol {} is not in the grammar", start));
+        let follow = Follow::new(&grammar, &first, start);
+        LL1TableBuilder {
+            grammar,
+            first,
+            follow,
+            table: LookupTable::new(),
+        }
+    }
+
+    pub(crate) fn try_new(grammar: &Grammar, start: &Term) -> Result<LL1TableBuilder> {
+        check_start(grammar, start)?;
+        check_end(grammar)?;
+        Ok(LL1TableBuilder::new(grammar, start))
+    }
+
+    /// Add A -> α to M[A, a]
+    fn insert(&mut self, lhs: SymbolId, a: SymbolId, production: ProductionId) {
+        debug!(
+            "[LL1 Builder] M[{}, {}] = r{}: {}",
+            self.grammar.term(lhs),
+            self.grammar.term(a),
+            production,
+            self.grammar.display(production)
+        );
+        match self.table.get_mut(&lhs, &a) {
+            Some(productions) => {
+                if !productions.contains(&production) {
+                    productions.push(production)
+                }
+            }
+            None => self.table.set(lhs, a, vec![production]),
+        }
+    }
+
+    fn build_table(&mut self) {
+        let productions = self
+            .grammar
+            .productions()
+            .map(|(id, production)| (id, production.lhs, production.rhs.clone()))
+            .collect::<Vec<_>>();
+        for (production, lhs, rhs) in productions {
+            // Rule 1: for each terminal a in First(α), add A -> α to M[A, a]
+            for a in self.first.first_of(rhs.iter().copied()) {
+                self.insert(lhs, a, production);
+            }
+            // Rule 2: if ε in First(α), for each b in Follow(A), add A -> α to M[A, b]
+            if self.first.is_nullable_seq(rhs.iter().copied()) {
+                for b in self.follow.follow_of(lhs).clone() {
+                    self.insert(lhs, b, production);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn build(mut self) -> LL1Table {
+        self.build_table();
+
+        let grammar = &self.grammar;
+        let conflicts = self
+            .table
+            .iter()
+            .filter(|(_, _, productions)| productions.len() > 1)
+            .map(|(nonterminal, lookahead, productions)| LL1Conflict {
+                nonterminal: grammar.term(*nonterminal).clone(),
+                lookahead: grammar.term(*lookahead).clone(),
+                productions: productions
+                    .iter()
+                    .map(|id| (*id, grammar.rule(*id).1.clone()))
+                    .collect(),
+            })
+            .sorted_by(|a, b| (&a.nonterminal, &a.lookahead).cmp(&(&b.nonterminal, &b.lookahead)))
+            .inspect(|conflict| warn!("{}", conflict))
+            .collect();
+
+        LL1Table {
+            grammar: self.grammar,
+            table: self.table,
+            conflicts,
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use crate::error::{Error, Result};
+use crate::ir::grammar::{InternedGrammar, ProductionId};
+use crate::ir::symbol::SymbolId;
+use crate::ll1::builder::LL1TableBuilder;
+use crate::utils::table::Table as LookupTable;
+use bnf::{Expression, Grammar, Term};
+use itertools::Itertools;
+use std::fmt::{Display, Formatter};
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// A single alternative `A -> α` of the grammar, as the id of the production and `α`,
+/// the lhs `A` is the nonterminal of the row
+pub type LL1Production = (ProductionId, Expression);
+
+/// A cell M[A, a] of the table that more than one production competes for
+#[derive(Debug, Clone)]
+pub struct LL1Conflict {
+    pub(crate) nonterminal: Term,
+    pub(crate) lookahead: Term,
+    pub(crate) productions: Vec<LL1Production>,
+}
+
+impl LL1Conflict {
+    pub fn nonterminal(&self) -> &Term {
+        &self.nonterminal
+    }
+
+    pub fn lookahead(&self) -> &Term {
+        &self.lookahead
+    }
+
+    pub fn productions(&self) -> &[LL1Production] {
+        &self.productions
+    }
+}
+
+impl Display for LL1Conflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "LL(1) conflict at M[{}, {}]: [{}]",
+            self.nonterminal,
+            self.lookahead,
+            self.productions
+                .iter()
+                .map(|(_, rhs)| format!("{} -> {}", self.nonterminal, rhs))
+                .join(", ")
+        ))
+    }
+}
+
+/// The predictive parsing table M[A, a], it owns the grammar the productions refer to
+pub struct LL1Table {
+    pub(crate) grammar: InternedGrammar,
+    pub(crate) table: LookupTable<SymbolId, SymbolId, Vec<ProductionId>>,
+    pub(crate) conflicts: Vec<LL1Conflict>,
+}
+
+impl LL1Table {
+    pub fn new(grammar: &Grammar, start: &Term) -> LL1Table {
+        LL1TableBuilder::new(grammar, start).build()
+    }
+
+    /// Same as [`LL1Table::new`], but fail if `start` or a nonterminal used is not defined,
+    /// or if a terminal of the grammar is `'$'`
+    pub fn try_new(grammar: &Grammar, start: &Term) -> Result<LL1Table> {
+        Ok(LL1TableBuilder::try_new(grammar, start)?.build())
+    }
+
+    pub fn grammar(&self) -> &InternedGrammar {
+        &self.grammar
+    }
+
+    /// The productions in M[A, a], more than one if the cell is in conflict
+    pub fn get(&self, nonterminal: &Term, lookahead: &Term) -> &[ProductionId] {
+        let (Some(nonterminal), Some(lookahead)) =
+            (self.grammar.id(nonterminal), self.grammar.id(lookahead))
+        else {
+            return &[];
+        };
+        self.table
+            .get(&nonterminal, &lookahead)
+            .map_or(&[], |productions| productions.as_slice())
+    }
+
+    /// All the conflicts found while building the table,
+    /// the grammar is LL(1) if and only if there is none
+    pub fn conflicts(&self) -> &[LL1Conflict] {
+        &self.conflicts
+    }
+
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+
+    /// Fail with the first conflict, if any
+    pub fn check_conflicts(&self) -> Result<()> {
+        match self.conflicts.first() {
+            Some(conflict) => Err(Error::from(conflict)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn parsing_table(&self) -> Table {
+        let grammar = &self.grammar;
+        let by_term = |a: &SymbolId, b: &SymbolId| grammar.term(*a).cmp(grammar.term(*b));
+        // terminals of the grammar, followed by `$`
+        let terminals = grammar
+            .terminals()
+            .sorted_by(by_term)
+            .chain(std::iter::once(grammar.end()))
+            .collect::<Vec<_>>();
+
+        let mut builder = Builder::default();
+        builder.push_record(
+            std::iter::once(String::from("Nonterminal/Terminal"))
+                .chain(terminals.iter().map(|t| grammar.term(*t).to_string())),
+        );
+
+        for nonterminal in grammar.nonterminals().sorted_by(by_term) {
+            let row = std::iter::once(grammar.term(nonterminal).to_string())
+                .chain(terminals.iter().map(|terminal| {
+                    self.table
+                        .get(&nonterminal, terminal)
+                        .into_iter()
+                        .flatten()
+                        .map(|id| {
+                            let (lhs, rhs) = grammar.rule(*id);
+                            format!("{} -> {}", lhs, rhs)
+                        })
+                        .join("\n")
+                }))
+                .collect::<Vec<_>>();
+            builder.push_record(row);
+        }
+
+        builder.build()
+    }
+
+    pub fn conflict_table(&self) -> Table {
+        let mut builder = Builder::default();
+        builder.push_record(["Nonterminal", "Lookahead", "Productions"]);
+        for conflict in self.conflicts.iter() {
+            builder.push_record([
+                conflict.nonterminal.to_string(),
+                conflict.lookahead.to_string(),
+                conflict
+                    .productions
+                    .iter()
+                    .map(|(_, rhs)| format!("{} -> {}", conflict.nonterminal, rhs))
+                    .join("\n"),
+            ]);
+        }
+        builder.build()
+    }
+}
+
+impl Display for LL1Table {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Table: \n{}", self.parsing_table()))?;
+        if self.has_conflicts() {
+            f.write_fmt(format_args!("\nConflicts: \n{}", self.conflict_table()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ll1::core::LL1Table;
+    use bnf::Term;
+    use std::str::FromStr;
+
+    #[test]
+    fn it_works() {
+        let grammar = r#"
+        <E> ::= <T> <E'>
+        <E'> ::= '+' <T> <E'> | 'ε'
+        <T> ::= <F> <T'>
+        <T'> ::= '*' <F> <T'> | 'ε'
+        <F> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+        let start = Term::from_str("<E>").unwrap();
+        let ll1 = LL1Table::new(&grammar, &start);
+        assert!(!ll1.has_conflicts());
+        insta::assert_snapshot!(ll1);
+    }
+
+    #[test]
+    fn left_recursion_conflict() {
+        let grammar = r#"
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+        let start = Term::from_str("<E>").unwrap();
+        let ll1 = LL1Table::new(&grammar, &start);
+        assert_eq!(ll1.conflicts().len(), 2);
+        insta::assert_snapshot!(ll1.conflict_table());
+    }
+
+    #[test]
+    fn identical_alternatives() {
+        let grammar = r#"
+        <S> ::= 'x' | 'x'
+        "#
+        .parse()
+        .unwrap();
+        let start = Term::from_str("<S>").unwrap();
+        let ll1 = LL1Table::new(&grammar, &start);
+        // two productions, even though they are spelled the same
+        assert_eq!(ll1.conflicts().len(), 1);
+        let indices = ll1.conflicts()[0]
+            .productions()
+            .iter()
+            .map(|(id, _)| id.index())
+            .collect::<Vec<_>>();
+        assert_eq!(indices, vec![0, 1]);
+    }
+
+    #[test]
+    fn nullable_follow() {
+        let grammar = r#"
+        <S> ::= <A> <B> 'c'
+        <A> ::= 'a' | 'ε'
+        <B> ::= 'b' | 'ε'
+        "#
+        .parse()
+        .unwrap();
+        let start = Term::from_str("<S>").unwrap();
+        let ll1 = LL1Table::new(&grammar, &start);
+        assert!(!ll1.has_conflicts());
+
+        // 'c' follows <A> through the nullable <B>, so <A> -> 'ε' is taken on 'c'
+        let a = Term::from_str("<A>").unwrap();
+        let c = Term::from_str("'c'").unwrap();
+        let indices = ll1
+            .get(&a, &c)
+            .iter()
+            .map(|id| id.index())
+            .collect::<Vec<_>>();
+        assert_eq!(indices, vec![2]);
+    }
+}
@@ -1,74 +1,97 @@
+use crate::error::Result;
+use crate::ir::grammar::InternedGrammar;
+use crate::ir::symbol::SymbolId;
 use crate::lr0::core::{LR0Closure, LR0Item, LR0ItemSet};
-use crate::utils::symbols;
-use bnf::{Grammar, Production, Term};
+use crate::utils::augmentation::AugmentationError;
+use bnf::{Grammar, Production};
+use itertools::Itertools;
 use log::debug;
 use std::collections::{HashMap, VecDeque};
 
-pub struct LR0Builder<'grammar> {
-    grammar: &'grammar Grammar,
-    closures: Vec<LR0ItemSet<'grammar>>,
-    transitions: HashMap<(usize, &'grammar Term), usize>,
+pub struct LR0Builder {
+    grammar: InternedGrammar,
 }
 
-impl<'grammar> LR0Builder<'grammar> {
-    pub fn new(grammar: &'grammar Grammar) -> LR0Builder<'grammar> {
+impl LR0Builder {
+    pub fn new(grammar: &Grammar) -> LR0Builder {
         LR0Builder {
-            grammar,
-            closures: Vec::new(),
-            transitions: HashMap::new(),
+            grammar: InternedGrammar::new(grammar),
         }
     }
 
-    pub fn build(mut self, augmentation: &'grammar Production) -> LR0Closure {
-        let initial = LR0ItemSet::from_iter(vec![LR0Item::from_production(augmentation).unwrap()]);
-        self.build_closure(&initial);
-        self.build_transition();
-        LR0Closure {
-            closures: self.closures,
-            transitions: self.transitions,
-        }
+    pub fn build(self, augmentation: &Production) -> LR0Closure {
+        self.try_build(augmentation)
+            .unwrap_or_else(|e| panic!("{}", e))
     }
 
-    fn build_closure(&mut self, initial: &LR0ItemSet<'grammar>) {
-        let mut waiting = VecDeque::from([initial.closure(self.grammar)]);
-        self.closures.push(initial.closure(self.grammar));
-
-        while !waiting.is_empty() {
-            let cur = waiting.pop_front().unwrap();
-            symbols(self.grammar).for_each(|term| {
-                let goto = cur.goto(self.grammar, term);
-                if !goto.items.is_empty() && !self.contains(&goto) {
-                    debug!("[LR0 Closure Builder] goto({}, {}) = {}", cur, term, goto);
-                    self.closures.push(goto);
-                    waiting.push_back(self.closures.last().unwrap().clone());
-                }
-            });
-        }
+    /// Same as [`LR0Builder::build`], but fail if `augmentation` has not a single alternative,
+    /// the augmentation is added to the grammar if it is not there yet
+    pub fn try_build(mut self, augmentation: &Production) -> Result<LR0Closure> {
+        let start = self
+            .grammar
+            .find_or_insert(augmentation)
+            .ok_or_else(|| AugmentationError::Malformed(augmentation.clone()))?;
+        let grammar = &self.grammar;
+        let initial = LR0ItemSet::from_iter(vec![LR0Item::new(start)]).closure(grammar);
+        let (closures, transitions) = canonical_collection(
+            grammar,
+            initial,
+            |set, term| set.goto(grammar, term),
+            |set| set.items.is_empty(),
+        );
+        Ok(LR0Closure {
+            grammar: self.grammar,
+            augmentation: start,
+            closures,
+            transitions,
+        })
     }
+}
 
-    fn build_transition(&mut self) {
-        for closure in &self.closures {
-            for term in symbols(self.grammar) {
-                let goto = closure.goto(self.grammar, term);
-                let cur_index = self.index_of(closure);
-                if goto.items.is_empty() {
-                    debug!("LR0: goto(I_{}, {}) = ∅", cur_index, term);
-                    continue;
-                }
-                let goto_index = self.index_of(&goto);
-                self.transitions.insert((cur_index, term), goto_index);
-                debug!("LR0: goto(I_{}, {}) = I_{}", cur_index, term, goto_index);
+/// The canonical collection of the item sets reachable from `initial`, in the order
+/// they are found, and the `goto` transitions between them, for LR(0) and LR(1) items alike
+pub(crate) fn canonical_collection<S>(
+    grammar: &InternedGrammar,
+    initial: S,
+    goto: impl Fn(&S, SymbolId) -> S,
+    is_empty: impl Fn(&S) -> bool,
+) -> (Vec<S>, HashMap<(usize, SymbolId), usize>)
+where
+    S: PartialEq,
+{
+    // in the order of the terms, so that the states are numbered the same for any ids
+    let symbols = grammar
+        .symbols()
+        .iter()
+        .filter(|(id, _)| *id != grammar.end())
+        .sorted_by(|a, b| a.1.cmp(b.1))
+        .collect::<Vec<_>>();
+    let mut closures = vec![initial];
+    let mut transitions = HashMap::new();
+
+    let mut waiting = VecDeque::from([0]);
+    while let Some(from) = waiting.pop_front() {
+        for (id, term) in symbols.iter() {
+            let goto = goto(&closures[from], *id);
+            if is_empty(&goto) {
+                debug!("[Closure Builder] goto(I_{}, {}) = ∅", from, term);
+                continue;
             }
+            let to = match closures.iter().position(|set| *set == goto) {
+                Some(to) => to,
+                None => {
+                    debug!("[Closure Builder] new I_{}", closures.len());
+                    closures.push(goto);
+                    waiting.push_back(closures.len() - 1);
+                    closures.len() - 1
+                }
+            };
+            transitions.insert((from, *id), to);
+            debug!("[Closure Builder] goto(I_{}, {}) = I_{}", from, term, to);
         }
     }
 
-    fn contains(&self, set: &LR0ItemSet<'grammar>) -> bool {
-        self.closures.iter().any(|v| v == set)
-    }
-
-    fn index_of(&self, set: &LR0ItemSet<'grammar>) -> usize {
-        self.closures.iter().position(|v| v == set).unwrap()
-    }
+    (closures, transitions)
 }
 
 #[cfg(test)]
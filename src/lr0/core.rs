@@ -1,35 +1,55 @@
+use crate::error::Result;
+use crate::ir::grammar::{InternedGrammar, ProductionId};
+use crate::ir::symbol::SymbolId;
 use crate::lr0::builder::LR0Builder;
-use crate::lr0::lookup::Lookup;
-use bnf::{Expression, Grammar, Production, Term};
+use crate::utils::augmentation::validate_augmentation;
+use crate::utils::{check_end, check_grammar, epsilon};
+use bnf::{Grammar, Production};
 use itertools::Itertools;
 use std::collections::{HashMap, HashSet};
-use std::fmt::{Display, Formatter};
 use std::hash::Hash;
 
 use tabled::builder::Builder;
 use tabled::Table;
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub struct LR0Item<'grammar> {
-    pub(crate) lhs: &'grammar Term,
-    pub(crate) rhs: &'grammar Expression,
+/// The production `production` with a dot after its first `delimiter` symbols
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct LR0Item {
+    pub(crate) production: ProductionId,
     pub(crate) delimiter: usize,
 }
 
-pub struct LR0Closure<'grammar> {
-    pub(crate) closures: Vec<LR0ItemSet<'grammar>>,
-    pub(crate) transitions: HashMap<(usize, &'grammar Term), usize>,
+/// The canonical collection of LR(0) item sets, it owns the grammar the items refer to
+pub struct LR0Closure {
+    pub(crate) grammar: InternedGrammar,
+    /// the augmentation `<S'> ::= <S>`
+    pub(crate) augmentation: ProductionId,
+    pub(crate) closures: Vec<LR0ItemSet>,
+    pub(crate) transitions: HashMap<(usize, SymbolId), usize>,
 }
 
-impl<'grammar> LR0Closure<'grammar> {
-    pub fn new(
-        grammar: &'grammar Grammar,
-        augmentation: &'grammar Production,
-    ) -> LR0Closure<'grammar> {
+impl LR0Closure {
+    pub fn new(grammar: &Grammar, augmentation: &Production) -> LR0Closure {
         LR0Builder::new(grammar).build(augmentation)
     }
 
-    pub fn closures(&self) -> &Vec<LR0ItemSet<'grammar>> {
+    /// Same as [`LR0Closure::new`], but fail if the grammar or the augmentation is invalid
+    pub fn try_new(grammar: &Grammar, augmentation: &Production) -> Result<LR0Closure> {
+        validate_augmentation(grammar, augmentation)?;
+        check_grammar(grammar)?;
+        check_end(grammar)?;
+        LR0Builder::new(grammar).try_build(augmentation)
+    }
+
+    pub fn grammar(&self) -> &InternedGrammar {
+        &self.grammar
+    }
+
+    pub fn augmentation(&self) -> ProductionId {
+        self.augmentation
+    }
+
+    pub fn closures(&self) -> &Vec<LR0ItemSet> {
         &self.closures
     }
 
@@ -37,7 +57,7 @@ impl<'grammar> LR0Closure<'grammar> {
         let mut builder = Builder::default();
         builder.push_record(["LR0 Closure"]); // header
         for set in self.closures.iter() {
-            builder.push_record([set.to_string()]);
+            builder.push_record([set.display(&self.grammar)]);
         }
         builder.index().build()
     }
@@ -48,44 +68,18 @@ impl<'grammar> LR0Closure<'grammar> {
         self.transitions
             .keys()
             .map(|(i, _)| *i)
-            .chain(self.transitions.values().map(|i| *i))
+            .chain(self.transitions.values().copied())
             .unique()
             .sorted()
     }
 
     pub fn transition_table(&self) -> Table {
-        let mut builder = Builder::default();
-        let header = self
-            .transitions
-            .keys()
-            .map(|(_, t)| t)
-            .unique()
-            .sorted()
-            .collect::<Vec<_>>();
-        // header
-        builder.push_record(
-            std::iter::once(String::from("Closure/Symbol"))
-                .chain(header.iter().map(|t| t.to_string())),
-        );
-
-        self.enumerate_states().for_each(|from| {
-            let row = std::iter::once(format!("I_{}", from))
-                .chain(header.iter().map(|term| {
-                    match self.transition(from, term) {
-                        None => String::new(), /* ∅ */
-                        Some(to) => format!("I_{}", to),
-                    }
-                }))
-                .collect::<Vec<_>>();
-            builder.push_record(row);
-        });
-
-        builder.build()
+        transition_table(&self.grammar, &self.transitions)
     }
 
     /// Iterate all the `LR0Set` in `LR0Closure`
     /// return the `LR0Item` with its' `LR0Set` index
-    pub fn enumerate_lr0<'a>(&'a self) -> impl Iterator<Item = (usize, &'a LR0Item<'grammar>)> {
+    pub fn enumerate_lr0(&self) -> impl Iterator<Item = (usize, &LR0Item)> {
         self.closures
             .iter()
             .enumerate()
@@ -97,96 +91,143 @@ impl<'grammar> LR0Closure<'grammar> {
         self.closures.len()
     }
 
-    pub fn transition<'a>(&self, from: usize, via: &Term) -> Option<usize> {
+    pub fn is_empty(&self) -> bool {
+        self.closures.is_empty()
+    }
+
+    pub fn transition(&self, from: usize, via: SymbolId) -> Option<usize> {
         self.transitions.get(&(from, via)).copied()
     }
 }
 
-impl<'grammar> LR0Item<'grammar> {
-    pub fn from_production(production: &'grammar Production) -> Option<LR0Item<'grammar>> {
-        if production.rhs_iter().count() != 1 {
-            return None;
-        }
-        let rhs = production.rhs_iter().next().unwrap();
-        Some(LR0Item {
-            lhs: &production.lhs,
-            rhs,
+/// Render the `goto` transitions between item sets,
+/// one row per state and one column per symbol
+pub(crate) fn transition_table(
+    grammar: &InternedGrammar,
+    transitions: &HashMap<(usize, SymbolId), usize>,
+) -> Table {
+    let mut builder = Builder::default();
+    let header = transitions
+        .keys()
+        .map(|(_, t)| *t)
+        .unique()
+        .sorted_by(|a, b| grammar.term(*a).cmp(grammar.term(*b)))
+        .collect::<Vec<_>>();
+    // header
+    builder.push_record(
+        std::iter::once(String::from("Closure/Symbol"))
+            .chain(header.iter().map(|t| grammar.term(*t).to_string())),
+    );
+
+    transitions
+        .keys()
+        .map(|(i, _)| *i)
+        .chain(transitions.values().copied())
+        .unique()
+        .sorted()
+        .for_each(|from| {
+            let row = std::iter::once(format!("I_{}", from))
+                .chain(header.iter().map(|term| {
+                    match transitions.get(&(from, *term)) {
+                        None => String::new(), /* ∅ */
+                        Some(to) => format!("I_{}", to),
+                    }
+                }))
+                .collect::<Vec<_>>();
+            builder.push_record(row);
+        });
+
+    builder.build()
+}
+
+impl LR0Item {
+    /// The item `lhs -> • rhs` of the production `production`
+    pub fn new(production: ProductionId) -> LR0Item {
+        LR0Item {
+            production,
             delimiter: 0,
-        })
+        }
+    }
+
+    pub fn production(&self) -> ProductionId {
+        self.production
     }
-}
 
-impl<'grammar> Display for LR0Item<'grammar> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    /// The number of symbols before the dot
+    pub fn delimiter(&self) -> usize {
+        self.delimiter
+    }
+
+    /// Move the dot over the next symbol
+    pub(crate) fn advance(&self) -> LR0Item {
+        LR0Item {
+            delimiter: self.delimiter + 1,
+            ..*self
+        }
+    }
+
+    pub fn lhs(&self, grammar: &InternedGrammar) -> SymbolId {
+        grammar.production(self.production).lhs
+    }
+
+    /// The symbol after the dot, `None` if the item is complete
+    pub fn expect(&self, grammar: &InternedGrammar) -> Option<SymbolId> {
+        grammar
+            .production(self.production)
+            .rhs
+            .get(self.delimiter)
+            .copied()
+    }
+
+    /// `lhs -> α • β` with the terms of `grammar`, `A -> 'ε' •` for an ε-production
+    pub fn display(&self, grammar: &InternedGrammar) -> String {
+        let production = grammar.production(self.production);
         let mut s = Vec::new();
-        s.push(self.lhs.to_string());
+        s.push(grammar.term(production.lhs).to_string());
         s.push("->".to_string());
-        let mut rhs = self
+        let mut rhs = production
             .rhs
-            .terms_iter()
-            .map(|t| t.to_string())
+            .iter()
+            .map(|t| grammar.term(*t).to_string())
             .collect::<Vec<_>>();
         rhs.insert(self.delimiter, "•".to_string());
+        if production.is_epsilon() {
+            rhs.insert(0, epsilon().to_string());
+        }
         s.extend(rhs);
-        f.write_str(&s.join(" "))
+        s.join(" ")
     }
 }
 
-impl<'grammar> Display for LR0ItemSet<'grammar> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let s = self
-            .items
-            .iter()
-            .map(|i| i.to_string())
-            .sorted() // make display result deterministic
-            .collect::<Vec<_>>()
-            .join(", ");
-        f.write_fmt(format_args!("[{}]", s))
-    }
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct LR0ItemSet {
+    pub(crate) items: HashSet<LR0Item>,
 }
 
-impl<'grammar> LR0Item<'grammar> {
-    pub fn expect(&self) -> Option<&'grammar Term> {
-        self.rhs.terms_iter().nth(self.delimiter)
-    }
-}
-
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub struct LR0ItemSet<'grammar> {
-    pub(crate) items: HashSet<LR0Item<'grammar>>,
-}
-
-impl<'grammar> FromIterator<LR0Item<'grammar>> for LR0ItemSet<'grammar> {
-    fn from_iter<T: IntoIterator<Item = LR0Item<'grammar>>>(iter: T) -> Self {
+impl FromIterator<LR0Item> for LR0ItemSet {
+    fn from_iter<T: IntoIterator<Item = LR0Item>>(iter: T) -> Self {
         Self {
             items: iter.into_iter().collect::<HashSet<_>>(),
         }
     }
 }
 
-impl<'grammar> LR0ItemSet<'grammar> {
+impl LR0ItemSet {
     pub fn new() -> Self {
-        Self {
-            items: HashSet::new(),
-        }
+        Self::default()
     }
-    pub fn closure(&self, grammar: &'grammar Grammar) -> LR0ItemSet<'grammar> {
-        let lookup = Lookup::new(grammar);
 
+    pub fn closure(&self, grammar: &InternedGrammar) -> LR0ItemSet {
         let mut closure = self.clone();
 
         loop {
             let mut extend = HashSet::new();
 
             for item in &closure.items {
-                if let Some(x) = item.rhs.terms_iter().nth(item.delimiter) {
+                if let Some(x) = item.expect(grammar) {
                     // x is the term after dot
-                    for production in lookup.get(x) {
-                        let lr0_item = LR0Item {
-                            lhs: production.0,
-                            rhs: production.1,
-                            delimiter: 0,
-                        };
+                    for (production, _) in grammar.productions_of(x) {
+                        let lr0_item = LR0Item::new(production);
                         if !closure.contains(&lr0_item) {
                             extend.insert(lr0_item);
                         }
@@ -205,15 +246,13 @@ impl<'grammar> LR0ItemSet<'grammar> {
         closure
     }
 
-    pub fn goto(&self, grammar: &'grammar Grammar, term: &Term) -> LR0ItemSet<'grammar> {
+    pub fn goto(&self, grammar: &InternedGrammar, term: SymbolId) -> LR0ItemSet {
         let items = self
             .items
             .iter()
             .filter_map(|item| {
-                if item.expect() == Some(term) {
-                    let mut bump = item.clone();
-                    bump.delimiter += 1;
-                    Some(bump)
+                if item.expect(grammar) == Some(term) {
+                    Some(item.advance())
                 } else {
                     None
                 }
@@ -223,15 +262,28 @@ impl<'grammar> LR0ItemSet<'grammar> {
         set.closure(grammar)
     }
 
-    pub fn contains(&self, item: &LR0Item<'grammar>) -> bool {
+    pub fn contains(&self, item: &LR0Item) -> bool {
         self.items.contains(item)
     }
+
+    /// `[item, ...]` with the terms of `grammar`, sorted
+    pub fn display(&self, grammar: &InternedGrammar) -> String {
+        let s = self
+            .items
+            .iter()
+            .map(|i| i.display(grammar))
+            .sorted() // make display result deterministic
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("[{}]", s)
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::ir::grammar::{InternedGrammar, ProductionId};
     use crate::lr0::core::{LR0Closure, LR0Item, LR0ItemSet};
-    use bnf::{Expression, Grammar, Production, Term};
+    use bnf::{Grammar, Production, Term};
     use std::str::FromStr;
 
     pub fn grammar() -> Grammar {
@@ -263,39 +315,25 @@ mod test {
 
     #[test]
     fn more_items() {
-        let set = [("<E'>", "<E>", 1usize), ("<E>", "<E> '+' <T>", 1usize)];
-        let set = set
-            .into_iter()
-            .map(|(lhs, rhs, delimiter)| {
-                let lhs = Term::from_str(lhs).unwrap();
-                let rhs = Expression::from_str(rhs).unwrap();
-                (lhs, rhs, delimiter)
-            })
-            .collect::<Vec<_>>();
-
+        let grammar = InternedGrammar::new(&grammar());
+        // <E'> -> <E> • and <E> -> <E> • '+' <T>
+        let set = [(0, 1usize), (1, 1usize)];
         let lr0_set: LR0ItemSet =
-            LR0ItemSet::from_iter(set.iter().map(|(lhs, rhs, delimiter)| LR0Item {
-                lhs,
-                rhs,
+            LR0ItemSet::from_iter(set.iter().map(|(production, delimiter)| LR0Item {
+                production: ProductionId(*production),
                 delimiter: *delimiter,
             }));
-        assert_eq!(lr0_set.closure(&grammar()).items.len(), 2);
+        assert_eq!(lr0_set.closure(&grammar).items.len(), 2);
     }
 
     #[test]
     fn goto() {
-        let grammar = grammar();
-        let lhs = Term::from_str("<E'>").unwrap();
-        let rhs = Expression::from_str("<E>").unwrap();
-
-        let lr0_item = LR0Item {
-            lhs: &lhs,
-            rhs: &rhs,
-            delimiter: 0,
-        };
+        let grammar = InternedGrammar::new(&grammar());
+        // <E'> -> • <E>
+        let lr0_item = LR0Item::new(ProductionId(0));
 
         let set = LR0ItemSet::from_iter(vec![lr0_item]);
-        let I_0 = set.closure(&grammar);
+        let i_0 = set.closure(&grammar);
         [
             ("<E>", 2usize),
             ("<T>", 2),
@@ -309,8 +347,8 @@ mod test {
         ]
         .iter()
         .for_each(|(t, cnt)| {
-            let term = Term::from_str(t).unwrap();
-            let goto = I_0.goto(&grammar, &term);
+            let term = grammar.id(&Term::from_str(t).unwrap()).unwrap();
+            let goto = i_0.goto(&grammar, term);
             assert_eq!(goto.items.len(), *cnt)
         });
     }
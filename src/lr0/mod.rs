@@ -1,3 +1,3 @@
 pub mod builder;
 pub mod core;
-pub mod lookup;
+pub mod table;
@@ -0,0 +1,124 @@
+use crate::error::Result;
+use crate::ir::symbol::SymbolId;
+use crate::lr0::core::LR0Closure;
+use crate::slr::conflict::ActionCandidates;
+use crate::slr::core::SLRTable;
+use crate::slr::precedence::Precedence;
+use bnf::{Grammar, Production};
+use log::info;
+use std::iter::once;
+
+/// Builds an LR(0) table, which reduces a completed item on every terminal,
+/// the result shares `SLRTable` so that both can be compared directly
+pub struct LR0TableBuilder {
+    terminals: Vec<SymbolId>,
+    closure: LR0Closure,
+    precedence: Precedence,
+}
+
+impl LR0TableBuilder {
+    /// `augmentation` `<S'> ::= <S>` must be the only production of `<S'>` in `grammar`,
+    /// [`augment`](crate::utils::augmentation::augment) synthesizes one
+    ///
+    /// Panics if the grammar or the augmentation is invalid, see [`LR0TableBuilder::try_new`]
+    pub fn new(grammar: &Grammar, augmentation: &Production) -> LR0TableBuilder {
+        Self::try_new(grammar, augmentation).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as [`LR0TableBuilder::new`], but fail if the grammar or the augmentation is invalid
+    pub fn try_new(grammar: &Grammar, augmentation: &Production) -> Result<LR0TableBuilder> {
+        // validates the grammar and the augmentation
+        let closure = LR0Closure::try_new(grammar, augmentation)?;
+        let terminals = closure
+            .grammar
+            .terminals()
+            .chain(once(closure.grammar.end()))
+            .collect();
+        info!("LR0 Closure:\n{}", closure.closure_table());
+        info!("LR0 Transition Table:\n{}", closure.transition_table());
+        Ok(LR0TableBuilder {
+            terminals,
+            closure,
+            precedence: Precedence::new(),
+        })
+    }
+
+    /// Resolve the shift/reduce conflicts by the precedence declarations
+    pub fn precedence(mut self, precedence: Precedence) -> LR0TableBuilder {
+        self.precedence = precedence;
+        self
+    }
+
+    pub fn build(self) -> SLRTable {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as [`LR0TableBuilder::build`], but fail if a transition is missing from the automaton,
+    /// or a `%prec` names a production out of the grammar
+    pub fn try_build(self) -> Result<SLRTable> {
+        let mut actions = ActionCandidates::new();
+        actions.fill(
+            self.closure.enumerate_lr0(),
+            |from, via| self.closure.transition(from, via),
+            self.closure.augmentation,
+            &self.closure.grammar,
+            |_, _, _| self.terminals.clone(),
+        )?;
+        let (table, conflicts) =
+            actions.resolve(self.closure.len(), &self.closure.grammar, &self.precedence)?;
+        Ok(SLRTable {
+            grammar: self.closure.grammar,
+            table,
+            conflicts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lr0::table::LR0TableBuilder;
+    use crate::slr::builder::SLRTableBuilder;
+    use crate::slr::conflict::ConflictKind;
+    use bnf::Production;
+    use std::str::FromStr;
+
+    #[test]
+    fn it_works() {
+        let grammar = r#"
+        <S'> ::= <S>
+        <S> ::= '(' <S> ')' | 'a'
+        "#
+        .parse()
+        .unwrap();
+
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+
+        let lr0 = LR0TableBuilder::new(&grammar, &augmentation).build();
+        assert!(!lr0.has_conflicts());
+        insta::assert_snapshot!(lr0);
+    }
+
+    #[test]
+    fn slr1_but_not_lr0() {
+        let grammar = r#"
+        <E'> ::= <E>
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= <T> '*' <F> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+
+        let lr0 = LR0TableBuilder::new(&grammar, &augmentation).build();
+        assert!(lr0
+            .conflicts()
+            .iter()
+            .all(|conflict| conflict.kind() == ConflictKind::ShiftReduce));
+        insta::assert_snapshot!(lr0.conflict_table());
+
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        assert!(!slr.has_conflicts());
+    }
+}
@@ -0,0 +1,75 @@
+use crate::error::Result;
+use crate::ir::grammar::InternedGrammar;
+use crate::lr0::builder::canonical_collection;
+use crate::lr0::core::LR0Item;
+use crate::lr1::core::{LR1Closure, LR1Item, LR1ItemSet};
+use crate::utils::augmentation::AugmentationError;
+use crate::utils::first::First;
+use bnf::{Grammar, Production};
+
+pub struct LR1Builder {
+    grammar: InternedGrammar,
+}
+
+impl LR1Builder {
+    pub fn new(grammar: &Grammar) -> LR1Builder {
+        LR1Builder {
+            grammar: InternedGrammar::new(grammar),
+        }
+    }
+
+    pub fn build(self, augmentation: &Production) -> LR1Closure {
+        self.try_build(augmentation)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as [`LR1Builder::build`], but fail if `augmentation` has not a single alternative,
+    /// the augmentation is added to the grammar if it is not there yet
+    pub fn try_build(mut self, augmentation: &Production) -> Result<LR1Closure> {
+        let start = self
+            .grammar
+            .find_or_insert(augmentation)
+            .ok_or_else(|| AugmentationError::Malformed(augmentation.clone()))?;
+        let grammar = &self.grammar;
+        let first = First::new(grammar);
+        let initial = LR1ItemSet::from_iter(vec![LR1Item::new(LR0Item::new(start), grammar.end())])
+            .closure(grammar, &first);
+        let (closures, transitions) = canonical_collection(
+            grammar,
+            initial,
+            |set, term| set.goto(grammar, &first, term),
+            |set| set.items.is_empty(),
+        );
+        Ok(LR1Closure {
+            grammar: self.grammar,
+            augmentation: start,
+            closures,
+            transitions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lr1::core::LR1Closure;
+    use bnf::Production;
+    use std::str::FromStr;
+
+    #[test]
+    fn it_works() {
+        let grammar = r#"
+        <S'> ::= <S>
+        <S> ::= <L> '=' <R> | <R>
+        <L> ::= '*' <R> | 'id'
+        <R> ::= <L>
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+
+        assert_eq!(
+            LR1Closure::new(&grammar, &augmentation).closures().len(),
+            14
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use crate::error::Result;
+use crate::ir::grammar::{InternedGrammar, ProductionId};
+use crate::ir::symbol::SymbolId;
+use crate::lr0::core::{transition_table, LR0Item};
+use crate::lr1::builder::LR1Builder;
+use crate::utils::augmentation::validate_augmentation;
+use crate::utils::first::First;
+use crate::utils::{check_end, check_grammar};
+use bnf::{Grammar, Production};
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+use std::iter::once;
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// An `LR0Item` with a lookahead terminal, i.e. [A -> α • β, a]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct LR1Item {
+    pub(crate) core: LR0Item,
+    pub(crate) lookahead: SymbolId,
+}
+
+/// The canonical collection of LR(1) item sets, it owns the grammar the items refer to
+pub struct LR1Closure {
+    pub(crate) grammar: InternedGrammar,
+    /// the augmentation `<S'> ::= <S>`
+    pub(crate) augmentation: ProductionId,
+    pub(crate) closures: Vec<LR1ItemSet>,
+    pub(crate) transitions: HashMap<(usize, SymbolId), usize>,
+}
+
+impl LR1Closure {
+    pub fn new(grammar: &Grammar, augmentation: &Production) -> LR1Closure {
+        LR1Builder::new(grammar).build(augmentation)
+    }
+
+    /// Same as [`LR1Closure::new`], but fail if the grammar or the augmentation is invalid
+    pub fn try_new(grammar: &Grammar, augmentation: &Production) -> Result<LR1Closure> {
+        validate_augmentation(grammar, augmentation)?;
+        check_grammar(grammar)?;
+        check_end(grammar)?;
+        LR1Builder::new(grammar).try_build(augmentation)
+    }
+
+    pub fn grammar(&self) -> &InternedGrammar {
+        &self.grammar
+    }
+
+    pub fn augmentation(&self) -> ProductionId {
+        self.augmentation
+    }
+
+    pub fn closures(&self) -> &Vec<LR1ItemSet> {
+        &self.closures
+    }
+
+    pub fn closure_table(&self) -> Table {
+        let mut builder = Builder::default();
+        builder.push_record(["LR1 Closure"]); // header
+        for set in self.closures.iter() {
+            builder.push_record([set.display(&self.grammar)]);
+        }
+        builder.index().build()
+    }
+
+    pub fn transition_table(&self) -> Table {
+        transition_table(&self.grammar, &self.transitions)
+    }
+
+    /// Iterate all the `LR1ItemSet` in `LR1Closure`
+    /// return the `LR1Item` with its' `LR1ItemSet` index
+    pub fn enumerate_lr1(&self) -> impl Iterator<Item = (usize, &LR1Item)> {
+        self.closures
+            .iter()
+            .enumerate()
+            .flat_map(|(i, set)| set.items.iter().map(move |item| (i, item)))
+    }
+
+    /// number of states in the closure
+    pub fn len(&self) -> usize {
+        self.closures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.closures.is_empty()
+    }
+
+    pub fn transition(&self, from: usize, via: SymbolId) -> Option<usize> {
+        self.transitions.get(&(from, via)).copied()
+    }
+}
+
+impl LR1Item {
+    pub fn new(core: LR0Item, lookahead: SymbolId) -> LR1Item {
+        LR1Item { core, lookahead }
+    }
+
+    pub fn core(&self) -> &LR0Item {
+        &self.core
+    }
+
+    pub fn lookahead(&self) -> SymbolId {
+        self.lookahead
+    }
+
+    pub fn expect(&self, grammar: &InternedGrammar) -> Option<SymbolId> {
+        self.core.expect(grammar)
+    }
+
+    /// `[lhs -> α • β, a]` with the terms of `grammar`
+    pub fn display(&self, grammar: &InternedGrammar) -> String {
+        format!(
+            "[{}, {}]",
+            self.core.display(grammar),
+            grammar.term(self.lookahead)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct LR1ItemSet {
+    pub(crate) items: HashSet<LR1Item>,
+}
+
+impl FromIterator<LR1Item> for LR1ItemSet {
+    fn from_iter<T: IntoIterator<Item = LR1Item>>(iter: T) -> Self {
+        Self {
+            items: iter.into_iter().collect::<HashSet<_>>(),
+        }
+    }
+}
+
+impl LR1ItemSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn closure(&self, grammar: &InternedGrammar, first: &First) -> LR1ItemSet {
+        let mut closure = self.clone();
+
+        loop {
+            let mut extend = HashSet::new();
+
+            for item in &closure.items {
+                if let Some(x) = item.expect(grammar) {
+                    // item is [A -> α • x β, a], x is the term after dot,
+                    // every b in First(β a) is a lookahead of x's productions
+                    let rhs = &grammar.production(item.core.production).rhs;
+                    let beta = rhs[item.core.delimiter + 1..].iter().copied();
+                    let lookaheads = first.first_of(beta.chain(once(item.lookahead)));
+                    for (production, _) in grammar.productions_of(x) {
+                        for lookahead in lookaheads.iter() {
+                            let lr1_item = LR1Item {
+                                core: LR0Item::new(production),
+                                lookahead: *lookahead,
+                            };
+                            if !closure.contains(&lr1_item) {
+                                extend.insert(lr1_item);
+                            }
+                        }
+                    }
+                }
+            }
+
+            // check if closure change or not
+            if extend.is_empty() {
+                break;
+            } else {
+                closure.items.extend(extend);
+            }
+        }
+
+        closure
+    }
+
+    pub fn goto(&self, grammar: &InternedGrammar, first: &First, term: SymbolId) -> LR1ItemSet {
+        let items = self
+            .items
+            .iter()
+            .filter_map(|item| {
+                if item.expect(grammar) == Some(term) {
+                    Some(LR1Item {
+                        core: item.core.advance(),
+                        lookahead: item.lookahead,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect::<HashSet<_>>();
+        let set = LR1ItemSet { items };
+        set.closure(grammar, first)
+    }
+
+    pub fn contains(&self, item: &LR1Item) -> bool {
+        self.items.contains(item)
+    }
+
+    /// The `LR0Item`s of the set with the lookaheads stripped
+    pub fn cores(&self) -> HashSet<&LR0Item> {
+        self.items.iter().map(|item| &item.core).collect()
+    }
+
+    /// `{item, ...}` with the terms of `grammar`, sorted
+    pub fn display(&self, grammar: &InternedGrammar) -> String {
+        let s = self
+            .items
+            .iter()
+            .map(|i| i.display(grammar))
+            .sorted() // make display result deterministic
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{{{}}}", s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ir::grammar::{InternedGrammar, ProductionId};
+    use crate::lr0::core::LR0Item;
+    use crate::lr1::core::{LR1Closure, LR1Item, LR1ItemSet};
+    use crate::utils::first::First;
+    use bnf::{Grammar, Production, Term};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <S'> ::= <S>
+        <S> ::= <L> '=' <R> | <R>
+        <L> ::= '*' <R> | 'id'
+        <R> ::= <L>
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn it_works() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+        let closure = LR1Closure::new(&grammar, &augmentation).closure_table();
+        insta::assert_snapshot!(closure);
+    }
+
+    #[test]
+    fn lookahead_propagation() {
+        let grammar = InternedGrammar::new(&grammar());
+        let first = First::new(&grammar);
+
+        // [<S'> -> • <S>, $]
+        let item = LR1Item::new(LR0Item::new(ProductionId(0)), grammar.end());
+        let i_0 = LR1ItemSet::from_iter(vec![item]).closure(&grammar, &first);
+        // <L> -> • '*' <R> and <L> -> • 'id' carry both '=' and '$'
+        assert_eq!(i_0.items.len(), 8);
+        assert_eq!(i_0.cores().len(), 6);
+
+        let l = grammar.id(&Term::from_str("<L>").unwrap()).unwrap();
+        let goto = i_0.goto(&grammar, &first, l);
+        assert_eq!(goto.items.len(), 2);
+        assert!(goto
+            .items
+            .iter()
+            .all(|item| item.lookahead() == grammar.end()));
+    }
+}
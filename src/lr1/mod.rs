@@ -0,0 +1,3 @@
+pub mod builder;
+pub mod core;
+pub mod table;
@@ -0,0 +1,150 @@
+use crate::error::Result;
+use crate::lr1::core::LR1Closure;
+use crate::slr::conflict::ActionCandidates;
+use crate::slr::core::SLRTable;
+use crate::slr::precedence::Precedence;
+use bnf::{Grammar, Production};
+use itertools::Itertools;
+use log::info;
+
+/// Builds the canonical LR(1) table,
+/// the result shares `SLRTable` so that both can be compared directly
+pub struct LR1TableBuilder {
+    closure: LR1Closure,
+    precedence: Precedence,
+}
+
+impl LR1TableBuilder {
+    /// `augmentation` `<S'> ::= <S>` must be the only production of `<S'>` in `grammar`,
+    /// [`augment`](crate::utils::augmentation::augment) synthesizes one
+    ///
+    /// Panics if the grammar or the augmentation is invalid, see [`LR1TableBuilder::try_new`]
+    pub fn new(grammar: &Grammar, augmentation: &Production) -> LR1TableBuilder {
+        Self::try_new(grammar, augmentation).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as [`LR1TableBuilder::new`], but fail if the grammar or the augmentation is invalid
+    pub fn try_new(grammar: &Grammar, augmentation: &Production) -> Result<LR1TableBuilder> {
+        // validates the grammar and the augmentation
+        let closure = LR1Closure::try_new(grammar, augmentation)?;
+        info!("LR1 Closure:\n{}", closure.closure_table());
+        info!("LR1 Transition Table:\n{}", closure.transition_table());
+        Ok(LR1TableBuilder {
+            closure,
+            precedence: Precedence::new(),
+        })
+    }
+
+    /// Resolve the shift/reduce conflicts by the precedence declarations
+    pub fn precedence(mut self, precedence: Precedence) -> LR1TableBuilder {
+        self.precedence = precedence;
+        self
+    }
+
+    pub fn build(self) -> SLRTable {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as [`LR1TableBuilder::build`], but fail if a transition is missing from the automaton,
+    /// or a `%prec` names a production out of the grammar
+    pub fn try_build(self) -> Result<SLRTable> {
+        let grammar = &self.closure.grammar;
+        let mut actions = ActionCandidates::new();
+        actions.fill(
+            self.closure
+                .enumerate_lr1()
+                .map(|(state, item)| (state, &item.core))
+                .unique(),
+            |from, via| self.closure.transition(from, via),
+            self.closure.augmentation,
+            grammar,
+            |state, _, core| {
+                // [A -> α •, a] is reduced on a
+                self.closure.closures[state]
+                    .items
+                    .iter()
+                    .filter(|item| &item.core == core)
+                    .map(|item| item.lookahead)
+                    .collect()
+            },
+        )?;
+        let (table, conflicts) = actions.resolve(self.closure.len(), grammar, &self.precedence)?;
+        Ok(SLRTable {
+            grammar: self.closure.grammar,
+            table,
+            conflicts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lr1::table::LR1TableBuilder;
+    use crate::parser::core::{tokens, Parser};
+    use crate::slr::builder::SLRTableBuilder;
+    use bnf::Production;
+    use std::str::FromStr;
+
+    #[test]
+    fn it_works() {
+        let grammar = r#"
+        <E'> ::= <E>
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= <T> '*' <F> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+
+        let lr1 = LR1TableBuilder::new(&grammar, &augmentation).build();
+        assert!(!lr1.has_conflicts());
+        assert_eq!(lr1.table.len(), 22);
+    }
+
+    #[test]
+    fn lr1_but_not_slr1() {
+        let grammar = r#"
+        <S'> ::= <S>
+        <S> ::= <L> '=' <R> | <R>
+        <L> ::= '*' <R> | 'id'
+        <R> ::= <L>
+        "#
+        .parse()
+        .unwrap();
+
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        assert!(slr.has_conflicts());
+
+        let lr1 = LR1TableBuilder::new(&grammar, &augmentation).build();
+        assert!(!lr1.has_conflicts());
+        insta::assert_snapshot!(lr1);
+    }
+
+    #[test]
+    fn identical_rhs() {
+        let grammar = r#"
+        <S'> ::= <S>
+        <S> ::= 'a' <A> 'd' | 'b' <B> 'd' | 'a' <B> 'e' | 'b' <A> 'e'
+        <A> ::= 'c'
+        <B> ::= 'c'
+        "#
+        .parse()
+        .unwrap();
+
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+
+        let lr1 = LR1TableBuilder::new(&grammar, &augmentation).build();
+        assert!(!lr1.has_conflicts());
+
+        let parser = Parser::new(&lr1);
+        // <A> -> 'c' is r5, <B> -> 'c' is r6
+        assert_eq!(parser.parse(tokens("a c d")), Ok(vec![5, 1]));
+        assert_eq!(parser.parse(tokens("a c e")), Ok(vec![6, 3]));
+        assert_eq!(parser.parse(tokens("b c d")), Ok(vec![6, 2]));
+        assert_eq!(parser.parse(tokens("b c e")), Ok(vec![5, 4]));
+    }
+}
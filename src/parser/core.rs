@@ -0,0 +1,434 @@
+use crate::parser::table::LRTable;
+use crate::parser::trace::ParseTrace;
+use crate::parser::tree::ParseTree;
+use crate::slr::core::SLRInstruction;
+use crate::utils::{dollar, epsilon};
+use bnf::{Expression, Term};
+use itertools::Itertools;
+use log::debug;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+/// Anything that can be fed to the [`Parser`] as a terminal
+pub trait Token {
+    fn terminal(&self) -> &Term;
+
+    /// The source text of the token, the name of its terminal by default
+    fn text(&self) -> &str {
+        match self.terminal() {
+            Term::Terminal(s) => s,
+            Term::Nonterminal(s) => s,
+        }
+    }
+
+    /// The byte span of the token in the source, if known
+    fn span(&self) -> Option<Range<usize>> {
+        None
+    }
+}
+
+impl Token for Term {
+    fn terminal(&self) -> &Term {
+        self
+    }
+}
+
+impl Token for &Term {
+    fn terminal(&self) -> &Term {
+        self
+    }
+}
+
+/// A token cut from the source text
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Lexeme {
+    pub(crate) terminal: Term,
+    pub(crate) text: String,
+    pub(crate) span: Range<usize>,
+}
+
+impl Lexeme {
+    pub fn new(terminal: Term, text: impl Into<String>, span: Range<usize>) -> Lexeme {
+        Lexeme {
+            terminal,
+            text: text.into(),
+            span,
+        }
+    }
+
+    /// Split the `source` by whitespace, every word is a terminal named by itself
+    pub fn split_whitespace(source: &str) -> Vec<Lexeme> {
+        source
+            .split_whitespace()
+            .map(|word| {
+                // every word is a subslice of source
+                let start = word.as_ptr() as usize - source.as_ptr() as usize;
+                Lexeme::new(
+                    Term::Terminal(word.to_string()),
+                    word,
+                    start..start + word.len(),
+                )
+            })
+            .collect()
+    }
+}
+
+impl Token for Lexeme {
+    fn terminal(&self) -> &Term {
+        &self.terminal
+    }
+
+    fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn span(&self) -> Option<Range<usize>> {
+        Some(self.span.clone())
+    }
+}
+
+/// The input can not be parsed by the table
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SyntaxError<'grammar> {
+    /// the state on top of the stack when the error occurs
+    pub(crate) state: usize,
+    /// the index of the unexpected token in the input
+    pub(crate) position: usize,
+    /// the unexpected token, `$` if the input ends too early
+    pub(crate) unexpected: Term,
+    /// the terminals which have an action in the row of `state`
+    pub(crate) expected: Vec<&'grammar Term>,
+}
+
+impl<'grammar> SyntaxError<'grammar> {
+    pub fn state(&self) -> usize {
+        self.state
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn unexpected(&self) -> &Term {
+        &self.unexpected
+    }
+
+    pub fn expected(&self) -> &[&'grammar Term] {
+        &self.expected
+    }
+}
+
+impl<'grammar> Display for SyntaxError<'grammar> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "syntax error at token {} in state I_{}: unexpected {}, expected one of {}",
+            self.position,
+            self.state,
+            self.unexpected,
+            self.expected.iter().join(", ")
+        ))
+    }
+}
+
+impl<'grammar> std::error::Error for SyntaxError<'grammar> {}
+
+/// A table-driven LR parser, works with every table implementing [`LRTable`]
+pub struct Parser<'table, T> {
+    table: &'table T,
+}
+
+impl<'table, T: LRTable> Parser<'table, T> {
+    pub fn new(table: &'table T) -> Parser<'table, T> {
+        Parser { table }
+    }
+
+    /// Parse the `tokens`, return the index of the productions
+    /// used by every reduction in order, i.e. the rightmost derivation in reverse
+    pub fn parse<I>(&self, tokens: I) -> Result<Vec<usize>, SyntaxError<'table>>
+    where
+        I: IntoIterator,
+        I::Item: Token,
+    {
+        let mut reductions = Vec::new();
+        self.run(tokens, |event| {
+            if let Event::Reduce { index, .. } = event {
+                reductions.push(index)
+            }
+        })?;
+        Ok(reductions)
+    }
+
+    /// Parse the `tokens`, build the concrete parse tree of the start symbol
+    pub fn parse_tree<I>(&self, tokens: I) -> Result<ParseTree<'table>, SyntaxError<'table>>
+    where
+        I: IntoIterator,
+        I::Item: Token,
+    {
+        let mut stack = Vec::new();
+        let (mut state, mut position) = (0, 0);
+        self.run(tokens, |event| match event {
+            Event::Shift { to, token } => {
+                stack.push(ParseTree::leaf(&token));
+                (state, position) = (to, position + 1);
+            }
+            Event::Reduce {
+                index,
+                lhs,
+                rhs,
+                goto,
+            } => {
+                let children = stack.split_off(stack.len() - arity(rhs));
+                stack.push(ParseTree::node(lhs, rhs, index, children));
+                state = goto;
+            }
+            Event::Accept => {}
+        })?;
+        // the augmentation <S'> -> <S> is never reduced, <S> is left alone,
+        // unless the table accepts before anything is on the stack
+        stack.pop().ok_or_else(|| SyntaxError {
+            state,
+            position,
+            unexpected: dollar().clone(),
+            expected: self.table.expected(state),
+        })
+    }
+
+    /// Parse the `tokens`, record every move of the parser
+    pub fn trace<I>(&self, tokens: I) -> ParseTrace<'table>
+    where
+        I: IntoIterator,
+        I::Item: Token,
+    {
+        let tokens = tokens.into_iter().collect::<Vec<_>>();
+        let mut trace = ParseTrace::new(tokens.iter().map(|token| token.terminal()));
+        let result = self.run(tokens, |event| trace.step(event));
+        trace.finish(result)
+    }
+
+    /// Run the shift/reduce/goto loop, notify `listener` on every shift and reduce
+    pub(crate) fn run<I, L>(&self, tokens: I, mut listener: L) -> Result<(), SyntaxError<'table>>
+    where
+        I: IntoIterator,
+        I::Item: Token,
+        L: FnMut(Event<'table, I::Item>),
+    {
+        let mut tokens = tokens.into_iter();
+        let mut states = vec![0usize];
+
+        let mut position = 0;
+        let mut lookahead = tokens.next();
+
+        loop {
+            let state = *states.last().unwrap();
+            let terminal = lookahead
+                .as_ref()
+                .map_or(dollar(), |token| token.terminal());
+            let error = || SyntaxError {
+                state,
+                position,
+                unexpected: terminal.clone(),
+                expected: self.table.expected(state),
+            };
+
+            match self.table.action(state, terminal) {
+                // nothing is left to shift at the end of the input
+                SLRInstruction::Shift(_) if lookahead.is_none() => return Err(error()),
+                SLRInstruction::Shift(to) => {
+                    debug!("[Parser] I_{}: shift {}, goto I_{}", state, terminal, to);
+                    let token = std::mem::replace(&mut lookahead, tokens.next());
+                    states.push(to);
+                    position += 1;
+                    if let Some(token) = token {
+                        listener(Event::Shift { to, token });
+                    }
+                }
+                SLRInstruction::Reduce(index) => {
+                    let (lhs, rhs) = self.table.production(index).ok_or_else(error)?;
+                    debug!("[Parser] I_{}: reduce by {} -> {}", state, lhs, rhs);
+                    // the bottom I_0 is never popped
+                    let count = arity(rhs);
+                    if count >= states.len() {
+                        return Err(error());
+                    }
+                    states.truncate(states.len() - count);
+
+                    let top = *states.last().unwrap();
+                    let goto = self.table.goto(top, lhs).ok_or_else(error)?;
+                    states.push(goto);
+                    listener(Event::Reduce {
+                        index,
+                        lhs,
+                        rhs,
+                        goto,
+                    });
+                }
+                SLRInstruction::Accept => {
+                    debug!("[Parser] I_{}: accept", state);
+                    listener(Event::Accept);
+                    return Ok(());
+                }
+                SLRInstruction::Goto(_) | SLRInstruction::Empty => return Err(error()),
+            }
+        }
+    }
+}
+
+/// The number of symbols `rhs` takes off the stack, 'ε' is never shifted
+pub(crate) fn arity(rhs: &Expression) -> usize {
+    rhs.terms_iter().filter(|term| *term != epsilon()).count()
+}
+
+/// What the [`Parser`] does in a single step
+pub(crate) enum Event<'grammar, T> {
+    /// shift `token`, goto `I_to`
+    Shift {
+        to: usize,
+        token: T,
+    },
+    /// reduce by the production `index`, i.e. `lhs -> rhs`, then goto `I_goto`
+    Reduce {
+        index: usize,
+        lhs: &'grammar Term,
+        rhs: &'grammar Expression,
+        goto: usize,
+    },
+    Accept,
+}
+
+/// Every whitespace-separated word of `input` as a terminal
+#[cfg(test)]
+pub(crate) fn tokens(input: &str) -> Vec<Term> {
+    input
+        .split_whitespace()
+        .map(|t| Term::Terminal(t.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lr1::table::LR1TableBuilder;
+    use crate::parser::core::{tokens, Parser};
+    use crate::parser::table::LRTable;
+    use crate::slr::builder::SLRTableBuilder;
+    use crate::slr::core::SLRInstruction;
+    use crate::utils::dollar;
+    use bnf::{Expression, Grammar, Production, Term};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <E'> ::= <E>
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= <T> '*' <F> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn it_works() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+
+        let parser = Parser::new(&slr);
+        assert_eq!(
+            parser.parse(tokens("id + id * id")),
+            Ok(vec![6, 4, 2, 6, 4, 6, 3, 1])
+        );
+        assert_eq!(
+            parser.parse(tokens("( id + id ) * id")),
+            Ok(vec![6, 4, 2, 6, 4, 1, 5, 4, 6, 3, 2])
+        );
+    }
+
+    #[test]
+    fn syntax_error() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let parser = Parser::new(&slr);
+
+        let error = parser.parse(tokens("id + * id")).unwrap_err();
+        assert_eq!(error.position(), 2);
+        assert_eq!(error.unexpected(), &Term::from_str("'*'").unwrap());
+        assert_eq!(
+            error.expected(),
+            &[
+                &Term::from_str("'('").unwrap(),
+                &Term::from_str("'id'").unwrap()
+            ]
+        );
+        insta::assert_snapshot!(error);
+
+        let error = parser.parse(tokens("( id")).unwrap_err();
+        assert_eq!(error.position(), 2);
+        assert_eq!(error.unexpected(), dollar());
+    }
+
+    #[test]
+    fn lr1_table() {
+        let grammar = r#"
+        <S'> ::= <S>
+        <S> ::= <L> '=' <R> | <R>
+        <L> ::= '*' <R> | 'id'
+        <R> ::= <L>
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+        let lr1 = LR1TableBuilder::new(&grammar, &augmentation).build();
+
+        let parser = Parser::new(&lr1);
+        assert!(parser.parse(tokens("* id = id")).is_ok());
+        assert!(parser.parse(tokens("id = = id")).is_err());
+    }
+
+    /// Reduces `<S> -> 'a'` before anything is shifted, and accepts on `$` right away
+    struct Malformed {
+        lhs: Term,
+        rhs: Expression,
+    }
+
+    impl LRTable for Malformed {
+        fn action(&self, _: usize, terminal: &Term) -> SLRInstruction {
+            match terminal == dollar() {
+                true => SLRInstruction::Accept,
+                false => SLRInstruction::Reduce(0),
+            }
+        }
+
+        fn actions(&self, _: usize, _: &Term) -> &[SLRInstruction] {
+            &[]
+        }
+
+        fn goto(&self, _: usize, _: &Term) -> Option<usize> {
+            Some(0)
+        }
+
+        fn production(&self, _: usize) -> Option<(&Term, &Expression)> {
+            Some((&self.lhs, &self.rhs))
+        }
+
+        fn expected(&self, _: usize) -> Vec<&Term> {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn malformed_table() {
+        let lhs = Term::from_str("<S>").unwrap();
+        let rhs = Expression::from_str("'a'").unwrap();
+        let table = Malformed { lhs, rhs };
+        let parser = Parser::new(&table);
+
+        // nothing to pop for <S> -> 'a'
+        let error = parser.parse(tokens("a")).unwrap_err();
+        assert_eq!(error.position(), 0);
+        // accepted, but no <S> on the stack
+        assert!(parser.parse(tokens("")).is_ok());
+        let error = parser.parse_tree(tokens("")).unwrap_err();
+        assert_eq!(error.unexpected(), dollar());
+    }
+}
@@ -0,0 +1,202 @@
+use crate::parser::forest::{ForestNode, NodeId, ParseForest};
+
+/// Chooses the derivations to keep at a node of a [`ParseForest`], see [`ParseForest::filter`]
+pub trait ForestFilter<'grammar> {
+    /// The derivations of `node` to keep, out of its packed nodes `packed`
+    fn select(
+        &self,
+        forest: &ParseForest<'grammar>,
+        node: NodeId,
+        packed: &[NodeId],
+    ) -> Vec<NodeId>;
+}
+
+impl<'grammar, F> ForestFilter<'grammar> for F
+where
+    F: Fn(&ParseForest<'grammar>, NodeId, &[NodeId]) -> Vec<NodeId>,
+{
+    fn select(
+        &self,
+        forest: &ParseForest<'grammar>,
+        node: NodeId,
+        packed: &[NodeId],
+    ) -> Vec<NodeId> {
+        self(forest, node, packed)
+    }
+}
+
+/// The production of a packed node
+fn production(forest: &ParseForest, packed: NodeId) -> usize {
+    match forest.node(packed) {
+        ForestNode::Packed { production, .. } => *production,
+        _ => unreachable!("a derivation is a packed node"),
+    }
+}
+
+/// Where a node has a derivation by one of the productions, drop the others
+pub struct PreferProduction {
+    productions: Vec<usize>,
+}
+
+impl PreferProduction {
+    pub fn new(productions: impl IntoIterator<Item = usize>) -> PreferProduction {
+        PreferProduction {
+            productions: productions.into_iter().collect(),
+        }
+    }
+}
+
+impl<'grammar> ForestFilter<'grammar> for PreferProduction {
+    fn select(&self, forest: &ParseForest<'grammar>, _: NodeId, packed: &[NodeId]) -> Vec<NodeId> {
+        let preferred = packed
+            .iter()
+            .copied()
+            .filter(|packed| self.productions.contains(&production(forest, *packed)))
+            .collect::<Vec<_>>();
+        match preferred.is_empty() {
+            true => packed.to_vec(),
+            false => preferred,
+        }
+    }
+}
+
+/// Drop every derivation by one of the productions
+pub struct RejectProduction {
+    productions: Vec<usize>,
+}
+
+impl RejectProduction {
+    pub fn new(productions: impl IntoIterator<Item = usize>) -> RejectProduction {
+        RejectProduction {
+            productions: productions.into_iter().collect(),
+        }
+    }
+}
+
+impl<'grammar> ForestFilter<'grammar> for RejectProduction {
+    fn select(&self, forest: &ParseForest<'grammar>, _: NodeId, packed: &[NodeId]) -> Vec<NodeId> {
+        packed
+            .iter()
+            .copied()
+            .filter(|packed| !self.productions.contains(&production(forest, *packed)))
+            .collect()
+    }
+}
+
+/// Keep the derivations whose leftmost child that differs derives the most tokens,
+/// e.g. a dangling `else` belongs to the nearest `if`
+pub struct LongestMatch;
+
+impl LongestMatch {
+    /// Where every child of the derivation `packed` ends, the greatest over the derivations
+    /// of its prefix
+    fn ends(forest: &ParseForest, packed: NodeId) -> Vec<usize> {
+        let ForestNode::Packed { left, right, .. } = forest.node(packed) else {
+            unreachable!("a derivation is a packed node")
+        };
+        let mut ends = left
+            .and_then(|left| {
+                forest
+                    .node(left)
+                    .packed()
+                    .iter()
+                    .map(|packed| Self::ends(forest, *packed))
+                    .max()
+            })
+            .unwrap_or_default();
+        ends.extend(
+            right
+                .and_then(|right| forest.node(right).range())
+                .map(|range| range.end),
+        );
+        ends
+    }
+}
+
+impl<'grammar> ForestFilter<'grammar> for LongestMatch {
+    fn select(&self, forest: &ParseForest<'grammar>, _: NodeId, packed: &[NodeId]) -> Vec<NodeId> {
+        let ends = packed
+            .iter()
+            .map(|packed| Self::ends(forest, *packed))
+            .collect::<Vec<_>>();
+        let longest = ends.iter().max();
+        packed
+            .iter()
+            .zip(ends.iter())
+            .filter(|(_, ends)| Some(*ends) == longest)
+            .map(|(packed, _)| *packed)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::earley::core::Earley;
+    use crate::parser::core::tokens;
+    use crate::parser::filter::{LongestMatch, PreferProduction, RejectProduction};
+    use crate::parser::forest::{NodeId, ParseForest};
+    use bnf::Grammar;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <S> ::= 'if' <E> 'then' <S> 'else' <S> | 'if' <E> 'then' <S> | 's'
+        <E> ::= 'e'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn longest_match() {
+        let grammar = grammar();
+        let earley = Earley::new(&grammar);
+
+        let mut forest = earley
+            .parse(tokens("if e then if e then s else s"))
+            .forest();
+        assert_eq!(forest.count(), Some(2));
+        forest.filter(&LongestMatch);
+        assert_eq!(forest.count(), Some(1));
+        assert!(!forest.is_ambiguous());
+        // the else belongs to the inner if
+        let tree = forest.tree().unwrap();
+        assert_eq!(tree.production(), Some(1));
+        insta::assert_snapshot!(tree);
+    }
+
+    #[test]
+    fn production() {
+        let grammar: Grammar = r#"
+        <S> ::= <A> | <B>
+        <A> ::= 'x'
+        <B> ::= 'x'
+        "#
+        .parse()
+        .unwrap();
+        let earley = Earley::new(&grammar);
+        let forest = earley.parse(tokens("x")).forest();
+        assert_eq!(forest.count(), Some(2));
+
+        let mut preferred = forest.clone();
+        preferred.filter(&PreferProduction::new([1]));
+        assert_eq!(preferred.count(), Some(1));
+        assert_eq!(
+            preferred.tree().unwrap().children()[0].production(),
+            Some(3)
+        );
+
+        let mut rejected = forest.clone();
+        rejected.filter(&RejectProduction::new([1]));
+        assert_eq!(rejected.count(), Some(1));
+        assert_eq!(rejected.tree().unwrap().children()[0].production(), Some(2));
+        // unreachable nodes are dropped
+        assert!(rejected.len() < forest.len());
+
+        // a filter can be any closure, the input is gone with all its derivations
+        let mut empty = forest.clone();
+        empty.filter(&|_: &ParseForest, _: NodeId, _: &[NodeId]| vec![]);
+        assert_eq!(empty.root(), None);
+        assert_eq!(empty.count(), Some(0));
+        assert!(empty.is_empty());
+    }
+}
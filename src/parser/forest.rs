@@ -0,0 +1,721 @@
+use crate::parser::core::Token;
+use crate::parser::filter::ForestFilter;
+use crate::parser::tree::ParseTree;
+use crate::utils::epsilon;
+use bnf::{Expression, Term};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// A dense id of a node in a [`ParseForest`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct NodeId(pub(crate) usize);
+
+impl NodeId {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+impl Display for NodeId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("n{}", self.0))
+    }
+}
+
+/// A node of a [`ParseForest`], the symbols of a production never include `'ε'`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ForestNode<'grammar> {
+    /// the token at `position`
+    Leaf {
+        terminal: Term,
+        text: String,
+        span: Option<Range<usize>>,
+        position: usize,
+    },
+    /// `symbol` derives the tokens `start..end`, one packed node per derivation
+    Symbol {
+        symbol: &'grammar Term,
+        start: usize,
+        end: usize,
+        packed: Vec<NodeId>,
+    },
+    /// the first `dot` symbols of the production `lhs -> rhs` derive the tokens `start..end`,
+    /// one packed node per derivation
+    Intermediate {
+        lhs: &'grammar Term,
+        rhs: &'grammar Expression,
+        production: usize,
+        dot: usize,
+        start: usize,
+        end: usize,
+        packed: Vec<NodeId>,
+    },
+    /// a single derivation of its parent by the production `lhs -> rhs`,
+    /// `right` is the last symbol, `left` the intermediate node of the symbols before,
+    /// both are `None` for an ε-production
+    Packed {
+        lhs: &'grammar Term,
+        rhs: &'grammar Expression,
+        production: usize,
+        left: Option<NodeId>,
+        right: Option<NodeId>,
+    },
+}
+
+impl<'grammar> ForestNode<'grammar> {
+    /// The packed nodes of a symbol or intermediate node, empty otherwise
+    pub fn packed(&self) -> &[NodeId] {
+        match self {
+            ForestNode::Symbol { packed, .. } | ForestNode::Intermediate { packed, .. } => packed,
+            ForestNode::Leaf { .. } | ForestNode::Packed { .. } => &[],
+        }
+    }
+
+    /// The tokens derived by a leaf, symbol or intermediate node
+    pub fn range(&self) -> Option<Range<usize>> {
+        match self {
+            ForestNode::Leaf { position, .. } => Some(*position..*position + 1),
+            ForestNode::Symbol { start, end, .. } | ForestNode::Intermediate { start, end, .. } => {
+                Some(*start..*end)
+            }
+            ForestNode::Packed { .. } => None,
+        }
+    }
+
+    /// The packed nodes of a symbol or intermediate node,
+    /// the children of a packed node
+    pub fn children(&self) -> Vec<NodeId> {
+        match self {
+            ForestNode::Packed { left, right, .. } => {
+                left.iter().chain(right.iter()).copied().collect()
+            }
+            _ => self.packed().to_vec(),
+        }
+    }
+
+    fn key(&self) -> Option<ForestKey<'grammar>> {
+        match self {
+            ForestNode::Leaf { position, .. } => Some(ForestKey::Leaf(*position)),
+            ForestNode::Symbol {
+                symbol, start, end, ..
+            } => Some(ForestKey::Symbol(symbol, *start, *end)),
+            ForestNode::Intermediate {
+                production,
+                dot,
+                start,
+                end,
+                ..
+            } => Some(ForestKey::Intermediate(*production, *dot, *start, *end)),
+            ForestNode::Packed { .. } => None,
+        }
+    }
+
+    fn packed_mut(&mut self) -> &mut Vec<NodeId> {
+        match self {
+            ForestNode::Symbol { packed, .. } | ForestNode::Intermediate { packed, .. } => packed,
+            ForestNode::Leaf { .. } | ForestNode::Packed { .. } => {
+                unreachable!("only a symbol or intermediate node has derivations")
+            }
+        }
+    }
+}
+
+impl<'grammar> Display for ForestNode<'grammar> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ForestNode::Leaf {
+                terminal, position, ..
+            } => f.write_fmt(format_args!(
+                "({}, {}, {})",
+                terminal,
+                position,
+                position + 1
+            )),
+            ForestNode::Symbol {
+                symbol, start, end, ..
+            } => f.write_fmt(format_args!("({}, {}, {})", symbol, start, end)),
+            ForestNode::Intermediate {
+                lhs,
+                rhs,
+                dot,
+                start,
+                end,
+                ..
+            } => {
+                let mut terms = rhs
+                    .terms_iter()
+                    .filter(|term| *term != epsilon())
+                    .map(|term| term.to_string())
+                    .collect::<Vec<_>>();
+                terms.insert(*dot, "•".to_string());
+                f.write_fmt(format_args!(
+                    "({} -> {}, {}, {})",
+                    lhs,
+                    terms.join(" "),
+                    start,
+                    end
+                ))
+            }
+            ForestNode::Packed { production, .. } => f.write_fmt(format_args!("r{}", production)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+enum ForestKey<'grammar> {
+    Leaf(usize),
+    Symbol(&'grammar Term, usize, usize),
+    /// (production, dot, start, end)
+    Intermediate(usize, usize, usize, usize),
+}
+
+/// A shared packed parse forest, every derivation of the input in a single graph
+///
+/// Symbol and intermediate nodes are shared by all the derivations using them,
+/// the forest of a cyclic grammar is cyclic
+#[derive(Debug, Clone, Default)]
+pub struct ParseForest<'grammar> {
+    pub(crate) nodes: Vec<ForestNode<'grammar>>,
+    keys: HashMap<ForestKey<'grammar>, NodeId>,
+    pub(crate) root: Option<NodeId>,
+}
+
+impl<'grammar> ParseForest<'grammar> {
+    pub(crate) fn new() -> ParseForest<'grammar> {
+        Self::default()
+    }
+
+    /// The symbol node of the start symbol deriving the whole input,
+    /// `None` if the input is not in the language
+    pub fn root(&self) -> Option<NodeId> {
+        self.root
+    }
+
+    pub fn node(&self, id: NodeId) -> &ForestNode<'grammar> {
+        &self.nodes[id.0]
+    }
+
+    /// All the nodes with their id
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &ForestNode<'grammar>)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (NodeId(index), node))
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Whether some symbol or intermediate node has more than one derivation
+    pub fn is_ambiguous(&self) -> bool {
+        self.nodes.iter().any(|node| node.packed().len() > 1)
+    }
+
+    fn insert(&mut self, key: ForestKey<'grammar>, node: ForestNode<'grammar>) -> (NodeId, bool) {
+        if let Some(id) = self.keys.get(&key) {
+            return (*id, false);
+        }
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(node);
+        self.keys.insert(key, id);
+        (id, true)
+    }
+
+    /// The leaf of `token` at `position`
+    pub(crate) fn leaf<T: Token>(&mut self, position: usize, token: &T) -> NodeId {
+        let node = ForestNode::Leaf {
+            terminal: token.terminal().clone(),
+            text: token.text().to_string(),
+            span: token.span(),
+            position,
+        };
+        self.insert(ForestKey::Leaf(position), node).0
+    }
+
+    /// The node of `symbol` deriving `start..end`, and whether it is new
+    pub(crate) fn symbol(
+        &mut self,
+        symbol: &'grammar Term,
+        start: usize,
+        end: usize,
+    ) -> (NodeId, bool) {
+        let node = ForestNode::Symbol {
+            symbol,
+            start,
+            end,
+            packed: vec![],
+        };
+        self.insert(ForestKey::Symbol(symbol, start, end), node)
+    }
+
+    /// The node of the first `dot` symbols of `lhs -> rhs` deriving `start..end`,
+    /// and whether it is new
+    pub(crate) fn intermediate(
+        &mut self,
+        lhs: &'grammar Term,
+        rhs: &'grammar Expression,
+        production: usize,
+        dot: usize,
+        start: usize,
+        end: usize,
+    ) -> (NodeId, bool) {
+        let node = ForestNode::Intermediate {
+            lhs,
+            rhs,
+            production,
+            dot,
+            start,
+            end,
+            packed: vec![],
+        };
+        self.insert(ForestKey::Intermediate(production, dot, start, end), node)
+    }
+
+    /// Add a derivation to `parent`, unless it is already there
+    pub(crate) fn pack(
+        &mut self,
+        parent: NodeId,
+        lhs: &'grammar Term,
+        rhs: &'grammar Expression,
+        production: usize,
+        left: Option<NodeId>,
+        right: Option<NodeId>,
+    ) {
+        let node = ForestNode::Packed {
+            lhs,
+            rhs,
+            production,
+            left,
+            right,
+        };
+        if self.nodes[parent.0]
+            .packed()
+            .iter()
+            .any(|packed| self.nodes[packed.0] == node)
+        {
+            return;
+        }
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(node);
+        self.nodes[parent.0].packed_mut().push(id);
+    }
+
+    /// Add to `parent` the derivation by `lhs -> rhs` whose symbols derive `children` in order,
+    /// the intermediate nodes of its prefixes are shared with the other derivations
+    pub(crate) fn derive(
+        &mut self,
+        parent: NodeId,
+        lhs: &'grammar Term,
+        rhs: &'grammar Expression,
+        production: usize,
+        children: &[NodeId],
+    ) {
+        let Some((last, prefix)) = children.split_last() else {
+            // ε-production
+            self.pack(parent, lhs, rhs, production, None, None);
+            return;
+        };
+        let start = self.nodes[children[0].0].range().unwrap().start;
+        let mut left = None;
+        for (dot, child) in prefix.iter().enumerate() {
+            let end = self.nodes[child.0].range().unwrap().end;
+            let (id, _) = self.intermediate(lhs, rhs, production, dot + 1, start, end);
+            self.pack(id, lhs, rhs, production, left, Some(*child));
+            left = Some(id);
+        }
+        self.pack(parent, lhs, rhs, production, left, Some(*last));
+    }
+
+    /// The number of parse trees of the root, saturated at `usize::MAX`,
+    /// `None` if a cycle makes it infinite
+    pub fn count(&self) -> Option<usize> {
+        match self.root {
+            None => Some(0),
+            Some(root) => self.count_of(root, &mut HashMap::new()),
+        }
+    }
+
+    /// A node on the current path is memoized as `None` until it is counted
+    fn count_of(&self, id: NodeId, memo: &mut HashMap<NodeId, Option<usize>>) -> Option<usize> {
+        if let Some(count) = memo.get(&id) {
+            return *count;
+        }
+        memo.insert(id, None);
+        let count = match self.node(id) {
+            ForestNode::Leaf { .. } => Some(1),
+            ForestNode::Packed { .. } => self
+                .node(id)
+                .children()
+                .iter()
+                .try_fold(1usize, |product, child| {
+                    Some(product.saturating_mul(self.count_of(*child, memo)?))
+                }),
+            node => node.packed().iter().try_fold(0usize, |sum, packed| {
+                Some(sum.saturating_add(self.count_of(*packed, memo)?))
+            }),
+        };
+        memo.insert(id, count);
+        count
+    }
+
+    /// The first derivation of the root as a parse tree,
+    /// a derivation through a cycle is never chosen
+    pub fn tree(&self) -> Option<ParseTree<'grammar>> {
+        self.first_tree(self.root?, &mut vec![])
+    }
+
+    /// Every derivation of the root as a parse tree,
+    /// a derivation through a cycle is never chosen
+    pub fn trees(&self) -> Vec<ParseTree<'grammar>> {
+        match self.root {
+            None => vec![],
+            Some(root) => self.all_trees(root, &mut vec![]),
+        }
+    }
+
+    fn leaf_tree(&self, id: NodeId) -> Option<ParseTree<'grammar>> {
+        match self.node(id) {
+            ForestNode::Leaf {
+                terminal,
+                text,
+                span,
+                ..
+            } => Some(ParseTree::Leaf {
+                terminal: terminal.clone(),
+                text: text.clone(),
+                span: span.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn first_tree(&self, id: NodeId, path: &mut Vec<NodeId>) -> Option<ParseTree<'grammar>> {
+        match self.node(id) {
+            ForestNode::Leaf { .. } => self.leaf_tree(id),
+            ForestNode::Symbol { packed, .. } => {
+                if path.contains(&id) {
+                    return None;
+                }
+                path.push(id);
+                let tree = packed.iter().find_map(|packed| {
+                    let ForestNode::Packed {
+                        lhs,
+                        rhs,
+                        production,
+                        ..
+                    } = self.node(*packed)
+                    else {
+                        unreachable!()
+                    };
+                    let children = self.first_children(*packed, path)?;
+                    Some(ParseTree::node(lhs, rhs, *production, children))
+                });
+                path.pop();
+                tree
+            }
+            ForestNode::Intermediate { .. } | ForestNode::Packed { .. } => None,
+        }
+    }
+
+    /// The children of the first derivation of a packed node
+    fn first_children(
+        &self,
+        packed: NodeId,
+        path: &mut Vec<NodeId>,
+    ) -> Option<Vec<ParseTree<'grammar>>> {
+        let ForestNode::Packed { left, right, .. } = self.node(packed) else {
+            unreachable!()
+        };
+        let mut children = match left {
+            None => vec![],
+            Some(left) => self
+                .node(*left)
+                .packed()
+                .iter()
+                .find_map(|packed| self.first_children(*packed, path))?,
+        };
+        if let Some(right) = right {
+            children.push(self.first_tree(*right, path)?);
+        }
+        Some(children)
+    }
+
+    fn all_trees(&self, id: NodeId, path: &mut Vec<NodeId>) -> Vec<ParseTree<'grammar>> {
+        match self.node(id) {
+            ForestNode::Leaf { .. } => self.leaf_tree(id).into_iter().collect(),
+            ForestNode::Symbol { packed, .. } => {
+                if path.contains(&id) {
+                    return vec![];
+                }
+                path.push(id);
+                let mut trees = vec![];
+                for packed in packed {
+                    let ForestNode::Packed {
+                        lhs,
+                        rhs,
+                        production,
+                        ..
+                    } = self.node(*packed)
+                    else {
+                        unreachable!()
+                    };
+                    for children in self.all_children(*packed, path) {
+                        trees.push(ParseTree::node(lhs, rhs, *production, children));
+                    }
+                }
+                path.pop();
+                trees
+            }
+            ForestNode::Intermediate { .. } | ForestNode::Packed { .. } => vec![],
+        }
+    }
+
+    /// The children of every derivation of a packed node
+    fn all_children(
+        &self,
+        packed: NodeId,
+        path: &mut Vec<NodeId>,
+    ) -> Vec<Vec<ParseTree<'grammar>>> {
+        let ForestNode::Packed { left, right, .. } = self.node(packed) else {
+            unreachable!()
+        };
+        let mut prefixes = match left {
+            None => vec![vec![]],
+            Some(left) => {
+                let mut prefixes = vec![];
+                for packed in self.node(*left).packed() {
+                    prefixes.extend(self.all_children(*packed, path));
+                }
+                prefixes
+            }
+        };
+        if let Some(right) = right {
+            let rights = self.all_trees(*right, path);
+            prefixes = prefixes
+                .into_iter()
+                .cartesian_product(rights)
+                .map(|(mut children, right)| {
+                    children.push(right);
+                    children
+                })
+                .collect();
+        }
+        prefixes
+    }
+
+    /// Keep the derivations chosen by `filter` at every symbol and intermediate node,
+    /// then drop every derivation through a node left without any,
+    /// and every node no longer reachable from the root, the ids of the others change
+    pub fn filter<F: ForestFilter<'grammar> + ?Sized>(&mut self, filter: &F) {
+        for index in 0..self.nodes.len() {
+            let packed = self.nodes[index].packed().to_vec();
+            if packed.is_empty() {
+                continue;
+            }
+            let kept = filter.select(self, NodeId(index), &packed);
+            self.nodes[index]
+                .packed_mut()
+                .retain(|packed| kept.contains(packed));
+        }
+        // a derivation is gone with any of its children
+        loop {
+            let dead = self
+                .nodes
+                .iter()
+                .map(|node| {
+                    matches!(
+                        node,
+                        ForestNode::Symbol { .. } | ForestNode::Intermediate { .. }
+                    ) && node.packed().is_empty()
+                })
+                .collect::<Vec<_>>();
+            let mut changed = false;
+            for index in 0..self.nodes.len() {
+                let packed = self.nodes[index].packed().to_vec();
+                let alive = packed
+                    .iter()
+                    .filter(|packed| {
+                        self.nodes[packed.0]
+                            .children()
+                            .iter()
+                            .all(|child| !dead[child.0])
+                    })
+                    .copied()
+                    .collect::<Vec<_>>();
+                if alive.len() != packed.len() {
+                    *self.nodes[index].packed_mut() = alive;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        self.prune();
+    }
+
+    /// Drop every node unreachable from the root, keep the others in order
+    fn prune(&mut self) {
+        let root = self
+            .root
+            .filter(|root| !self.nodes[root.0].packed().is_empty());
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut stack = root.into_iter().collect::<Vec<_>>();
+        while let Some(id) = stack.pop() {
+            if !reachable[id.0] {
+                reachable[id.0] = true;
+                stack.extend(self.nodes[id.0].children());
+            }
+        }
+
+        let mut ids = vec![None; self.nodes.len()];
+        let mut nodes = vec![];
+        for (index, node) in self.nodes.drain(..).enumerate() {
+            if reachable[index] {
+                ids[index] = Some(NodeId(nodes.len()));
+                nodes.push(node);
+            }
+        }
+        let renumber = |id: &mut NodeId| *id = ids[id.0].unwrap();
+        for node in nodes.iter_mut() {
+            match node {
+                ForestNode::Packed { left, right, .. } => {
+                    left.iter_mut().chain(right.iter_mut()).for_each(renumber)
+                }
+                ForestNode::Symbol { packed, .. } | ForestNode::Intermediate { packed, .. } => {
+                    packed.iter_mut().for_each(renumber)
+                }
+                ForestNode::Leaf { .. } => {}
+            }
+        }
+        self.keys = nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, node)| Some((node.key()?, NodeId(index))))
+            .collect();
+        self.root = root.map(|root| ids[root.0].unwrap());
+        self.nodes = nodes;
+    }
+
+    /// The forest in the DOT language of Graphviz, every node with more than one derivation
+    /// is drawn in red, the root with a double border
+    pub fn graphviz(&self) -> String {
+        let mut dot = String::from("digraph forest {\n");
+        for (id, node) in self.nodes() {
+            let shape = match node {
+                ForestNode::Leaf { .. } => "box",
+                ForestNode::Symbol { .. } => "ellipse",
+                ForestNode::Intermediate { .. } => "box, style=rounded",
+                ForestNode::Packed { .. } => "circle",
+            };
+            let color = match node.packed().len() {
+                0 | 1 => "",
+                _ => ", color=red",
+            };
+            let peripheries = match self.root == Some(id) {
+                true => ", peripheries=2",
+                false => "",
+            };
+            let label = node.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+            dot.push_str(&format!(
+                "    {} [label=\"{}\", shape={}{}{}];\n",
+                id, label, shape, color, peripheries
+            ));
+            for child in node.children() {
+                dot.push_str(&format!("    {} -> {};\n", id, child));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// One row per node, with the packed nodes of every symbol and intermediate node
+    pub fn tabled(&self) -> Table {
+        let mut builder = Builder::default();
+        builder.push_record(["Node", "Label", "Children"]); // header
+        for (id, node) in self.nodes() {
+            let children = node.children().iter().join(", ");
+            builder.push_record([id.to_string(), node.to_string(), children]);
+        }
+        builder.build()
+    }
+}
+
+impl<'grammar> Display for ParseForest<'grammar> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.root {
+            None => f.write_str("Root: \n")?,
+            Some(root) => f.write_fmt(format_args!("Root: {}\n", root))?,
+        }
+        f.write_fmt(format_args!("Forest: \n{}", self.tabled()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::earley::core::Earley;
+    use crate::parser::core::tokens;
+    use bnf::Grammar;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <E> ::= <E> '+' <E> | <E> '*' <E> | '(' <E> ')' | 'id'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn trees() {
+        let grammar = grammar();
+        let earley = Earley::new(&grammar);
+
+        // Catalan(3) ways to bracket 4 operands
+        let forest = earley.parse(tokens("id + id + id + id")).forest();
+        assert_eq!(forest.count(), Some(5));
+        let trees = forest.trees();
+        assert_eq!(trees.len(), 5);
+        assert!(trees.iter().all(|tree| tree.text() == "id + id + id + id"));
+        assert!(trees
+            .iter()
+            .enumerate()
+            .all(|(i, a)| trees[i + 1..].iter().all(|b| a != b)));
+        assert_eq!(forest.tree().as_ref(), trees.first());
+
+        let forest = earley.parse(tokens("id +")).forest();
+        assert_eq!(forest.count(), Some(0));
+        assert!(forest.trees().is_empty());
+    }
+
+    #[test]
+    fn cycle() {
+        let grammar: Grammar = r#"
+        <S> ::= <S> | 'a'
+        "#
+        .parse()
+        .unwrap();
+        let earley = Earley::new(&grammar);
+
+        let forest = earley.parse(tokens("a")).forest();
+        assert_eq!(forest.count(), None);
+        assert_eq!(forest.trees().len(), 1);
+    }
+
+    #[test]
+    fn graphviz() {
+        let grammar = grammar();
+        let earley = Earley::new(&grammar);
+
+        let forest = earley.parse(tokens("id + id * id")).forest();
+        insta::assert_snapshot!(forest.graphviz());
+    }
+}
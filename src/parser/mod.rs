@@ -0,0 +1,6 @@
+pub mod core;
+pub mod filter;
+pub mod forest;
+pub mod table;
+pub mod trace;
+pub mod tree;
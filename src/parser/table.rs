@@ -0,0 +1,71 @@
+use crate::ir::grammar::ProductionId;
+use crate::slr::core::{ActionSet, SLRInstruction, SLRTable};
+use bnf::{Expression, Term};
+
+/// An LR parsing table which can drive a [`Parser`](crate::parser::core::Parser),
+/// the terms of a parse are borrowed from the table
+pub trait LRTable {
+    /// ACTION[state, terminal]
+    fn action(&self, state: usize, terminal: &Term) -> SLRInstruction;
+
+    /// Every action of the cell ACTION[state, terminal], more than one on a conflict
+    fn actions(&self, state: usize, terminal: &Term) -> &[SLRInstruction];
+
+    /// GOTO[state, nonterminal]
+    fn goto(&self, state: usize, nonterminal: &Term) -> Option<usize>;
+
+    /// The production used by `Reduce(index)`, as `(lhs, rhs)`
+    fn production(&self, index: usize) -> Option<(&Term, &Expression)>;
+
+    /// The terminals with a non-empty action in the row of `state`, sorted
+    fn expected(&self, state: usize) -> Vec<&Term>;
+}
+
+impl SLRTable {
+    fn lookup(&self, state: usize, term: &Term) -> Option<&ActionSet> {
+        self.cell(state, self.grammar.id(term)?)
+    }
+}
+
+impl LRTable for SLRTable {
+    fn action(&self, state: usize, terminal: &Term) -> SLRInstruction {
+        self.lookup(state, terminal)
+            .map(|cell| cell.chosen)
+            .filter(|instruction| !matches!(instruction, SLRInstruction::Goto(_)))
+            .unwrap_or(SLRInstruction::Empty)
+    }
+
+    fn actions(&self, state: usize, terminal: &Term) -> &[SLRInstruction] {
+        match self.lookup(state, terminal) {
+            Some(cell) if !matches!(cell.chosen, SLRInstruction::Goto(_)) => &cell.actions,
+            _ => &[],
+        }
+    }
+
+    fn goto(&self, state: usize, nonterminal: &Term) -> Option<usize> {
+        match self.lookup(state, nonterminal)?.chosen {
+            SLRInstruction::Goto(to) => Some(to),
+            _ => None,
+        }
+    }
+
+    fn production(&self, index: usize) -> Option<(&Term, &Expression)> {
+        (index < self.grammar.len()).then(|| self.grammar.rule(ProductionId(index as u32)))
+    }
+
+    fn expected(&self, state: usize) -> Vec<&Term> {
+        let mut expected = self
+            .table
+            .get(state)
+            .into_iter()
+            .flat_map(|row| row.iter())
+            .filter(|(id, cell)| {
+                self.grammar.symbols().is_terminal(**id)
+                    && !matches!(cell.chosen, SLRInstruction::Empty)
+            })
+            .map(|(id, _)| self.grammar.term(*id))
+            .collect::<Vec<_>>();
+        expected.sort();
+        expected
+    }
+}
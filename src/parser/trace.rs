@@ -0,0 +1,227 @@
+use crate::parser::core::{arity, Event, SyntaxError, Token};
+use crate::slr::core::SLRInstruction;
+use crate::utils::dollar;
+use bnf::{Expression, Term};
+use itertools::Itertools;
+use std::fmt::{Display, Formatter};
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// A single move of the LR parser, the configuration before the move and the move itself
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TraceStep<'grammar> {
+    /// the stack of states, bottom first
+    pub(crate) states: Vec<usize>,
+    /// the grammar symbols matching the stack of states, bottom first
+    pub(crate) symbols: Vec<Term>,
+    /// the remaining input, ends with `$`
+    pub(crate) input: Vec<Term>,
+    /// the action taken, `Empty` if the parser stops with an error
+    pub(crate) action: SLRInstruction,
+    /// the production of a `Reduce` action
+    pub(crate) production: Option<(&'grammar Term, &'grammar Expression)>,
+}
+
+impl<'grammar> TraceStep<'grammar> {
+    pub fn states(&self) -> &[usize] {
+        &self.states
+    }
+
+    pub fn symbols(&self) -> &[Term] {
+        &self.symbols
+    }
+
+    pub fn input(&self) -> &[Term] {
+        &self.input
+    }
+
+    pub fn action(&self) -> SLRInstruction {
+        self.action
+    }
+
+    fn action_to_string(&self) -> String {
+        match (self.action, self.production) {
+            (SLRInstruction::Shift(to), _) => format!("shift {}", to),
+            (SLRInstruction::Reduce(_), Some((lhs, rhs))) => {
+                format!("reduce by {} -> {}", lhs, rhs)
+            }
+            (SLRInstruction::Accept, _) => "accept".to_string(),
+            _ => "error".to_string(),
+        }
+    }
+}
+
+/// All the moves made by the [`Parser`](crate::parser::core::Parser) on an input,
+/// with the syntax error which stops it, if any
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseTrace<'grammar> {
+    pub(crate) steps: Vec<TraceStep<'grammar>>,
+    pub(crate) error: Option<SyntaxError<'grammar>>,
+    /// the configuration after the last recorded step
+    states: Vec<usize>,
+    symbols: Vec<Term>,
+    /// the whole input, ends with `$`
+    input: Vec<Term>,
+    /// the index of the next token in `input`
+    position: usize,
+}
+
+impl<'grammar> ParseTrace<'grammar> {
+    pub(crate) fn new<'a, I: IntoIterator<Item = &'a Term>>(input: I) -> ParseTrace<'grammar> {
+        ParseTrace {
+            steps: vec![],
+            error: None,
+            states: vec![0],
+            symbols: vec![],
+            input: input
+                .into_iter()
+                .cloned()
+                .chain(std::iter::once(dollar().clone()))
+                .collect(),
+            position: 0,
+        }
+    }
+
+    fn push(
+        &mut self,
+        action: SLRInstruction,
+        production: Option<(&'grammar Term, &'grammar Expression)>,
+    ) {
+        self.steps.push(TraceStep {
+            states: self.states.clone(),
+            symbols: self.symbols.clone(),
+            input: self.input[self.position..].to_vec(),
+            action,
+            production,
+        });
+    }
+
+    /// Record the configuration, then apply the move of `event` to it
+    pub(crate) fn step<T: Token>(&mut self, event: Event<'grammar, T>) {
+        match event {
+            Event::Shift { to, .. } => {
+                self.push(SLRInstruction::Shift(to), None);
+                self.states.push(to);
+                self.symbols.push(self.input[self.position].clone());
+                self.position += 1;
+            }
+            Event::Reduce {
+                index,
+                lhs,
+                rhs,
+                goto,
+            } => {
+                self.push(SLRInstruction::Reduce(index), Some((lhs, rhs)));
+                let count = arity(rhs);
+                self.states.truncate(self.states.len() - count);
+                self.symbols.truncate(self.symbols.len() - count);
+                self.states.push(goto);
+                self.symbols.push(lhs.clone());
+            }
+            Event::Accept => self.push(SLRInstruction::Accept, None),
+        }
+    }
+
+    /// Record the final configuration if the parser stops with an error
+    pub(crate) fn finish(mut self, result: Result<(), SyntaxError<'grammar>>) -> Self {
+        if let Err(error) = result {
+            self.push(SLRInstruction::Empty, None);
+            self.error = Some(error);
+        }
+        self
+    }
+
+    pub fn steps(&self) -> &[TraceStep<'grammar>] {
+        &self.steps
+    }
+
+    pub fn error(&self) -> Option<&SyntaxError<'grammar>> {
+        self.error.as_ref()
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// The textbook "moves of an LR parser" table
+    pub fn tabled(&self) -> Table {
+        let mut builder = Builder::default();
+        builder.push_record(["Stack", "Symbols", "Input", "Action"]); // header
+        for step in self.steps.iter() {
+            builder.push_record([
+                step.states.iter().join(" "),
+                std::iter::once(dollar())
+                    .chain(step.symbols.iter())
+                    .join(" "),
+                step.input.iter().join(" "),
+                step.action_to_string(),
+            ]);
+        }
+        builder.index().build()
+    }
+}
+
+impl<'grammar> Display for ParseTrace<'grammar> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Trace: \n{}", self.tabled()))?;
+        if let Some(error) = &self.error {
+            f.write_fmt(format_args!("\n{}", error))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::core::{tokens, Parser};
+    use crate::slr::builder::SLRTableBuilder;
+    use crate::slr::core::SLRInstruction;
+    use bnf::{Grammar, Production};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <E'> ::= <E>
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= <T> '*' <F> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn it_works() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let parser = Parser::new(&slr);
+
+        let trace = parser.trace(tokens("id * id + id"));
+        assert!(trace.is_accepted());
+        assert_eq!(trace.steps().len(), 14);
+        assert_eq!(trace.steps()[0].states(), &[0]);
+        assert_eq!(
+            trace.steps().last().unwrap().action(),
+            SLRInstruction::Accept
+        );
+        insta::assert_snapshot!(trace);
+    }
+
+    #[test]
+    fn syntax_error() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let parser = Parser::new(&slr);
+
+        let trace = parser.trace(tokens("( id + ) id"));
+        assert!(!trace.is_accepted());
+        assert_eq!(trace.error().unwrap().position(), 3);
+        assert_eq!(
+            trace.steps().last().unwrap().action(),
+            SLRInstruction::Empty
+        );
+        insta::assert_snapshot!(trace);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::parser::core::Token;
+use bnf::{Expression, Term};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// The concrete parse tree built by [`Parser::parse_tree`](crate::parser::core::Parser::parse_tree)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseTree<'grammar> {
+    /// an interior node, reduced by the production `lhs -> rhs`
+    Node {
+        lhs: &'grammar Term,
+        rhs: &'grammar Expression,
+        /// the index of the production in the grammar
+        production: usize,
+        children: Vec<ParseTree<'grammar>>,
+    },
+    /// a shifted token
+    Leaf {
+        terminal: Term,
+        text: String,
+        span: Option<Range<usize>>,
+    },
+}
+
+impl<'grammar> ParseTree<'grammar> {
+    pub(crate) fn node(
+        lhs: &'grammar Term,
+        rhs: &'grammar Expression,
+        production: usize,
+        children: Vec<ParseTree<'grammar>>,
+    ) -> ParseTree<'grammar> {
+        ParseTree::Node {
+            lhs,
+            rhs,
+            production,
+            children,
+        }
+    }
+
+    pub(crate) fn leaf<T: Token>(token: &T) -> ParseTree<'grammar> {
+        ParseTree::Leaf {
+            terminal: token.terminal().clone(),
+            text: token.text().to_string(),
+            span: token.span(),
+        }
+    }
+
+    /// The nonterminal of a node, or the terminal of a leaf
+    pub fn symbol(&self) -> &Term {
+        match self {
+            ParseTree::Node { lhs, .. } => lhs,
+            ParseTree::Leaf { terminal, .. } => terminal,
+        }
+    }
+
+    /// The index of the production of a node, `None` for a leaf
+    pub fn production(&self) -> Option<usize> {
+        match self {
+            ParseTree::Node { production, .. } => Some(*production),
+            ParseTree::Leaf { .. } => None,
+        }
+    }
+
+    /// The children of a node, empty for a leaf
+    pub fn children(&self) -> &[ParseTree<'grammar>] {
+        match self {
+            ParseTree::Node { children, .. } => children,
+            ParseTree::Leaf { .. } => &[],
+        }
+    }
+
+    /// The span of a leaf, or the smallest span covering all the children of a node,
+    /// `None` if no token below has a span
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            ParseTree::Leaf { span, .. } => span.clone(),
+            ParseTree::Node { children, .. } => children
+                .iter()
+                .filter_map(|child| child.span())
+                .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end)),
+        }
+    }
+
+    /// The source text covered by the tree, the text of all the non-empty leaves joined by space
+    pub fn text(&self) -> String {
+        match self {
+            ParseTree::Leaf { text, .. } => text.clone(),
+            ParseTree::Node { children, .. } => children
+                .iter()
+                .map(|child| child.text())
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
+    /// Iterate all the nodes and leaves in preorder, with their depth
+    pub fn preorder(&self) -> impl Iterator<Item = (usize, &ParseTree<'grammar>)> {
+        let mut stack = vec![(0, self)];
+        std::iter::from_fn(move || {
+            let (depth, tree) = stack.pop()?;
+            stack.extend(tree.children().iter().rev().map(|child| (depth + 1, child)));
+            Some((depth, tree))
+        })
+    }
+
+    /// One row per node or leaf in preorder, the symbol is indented by its depth
+    pub fn tabled(&self) -> Table {
+        let mut builder = Builder::default();
+        builder.push_record(["Symbol", "Production", "Text", "Span"]); // header
+        for (depth, tree) in self.preorder() {
+            let production = match tree {
+                ParseTree::Node {
+                    lhs,
+                    rhs,
+                    production,
+                    ..
+                } => format!("r{}: {} -> {}", production, lhs, rhs),
+                ParseTree::Leaf { .. } => String::new(),
+            };
+            builder.push_record([
+                format!("{}{}", "  ".repeat(depth), tree.symbol()),
+                production,
+                tree.text(),
+                span_to_string(tree.span()),
+            ]);
+        }
+        builder.build()
+    }
+
+    fn fmt_indented(&self, f: &mut Formatter<'_>, prefix: &str) -> std::fmt::Result {
+        match self {
+            ParseTree::Node { production, .. } => f.write_fmt(format_args!(
+                "{} (r{}) {}\n",
+                self.symbol(),
+                production,
+                span_to_string(self.span())
+            ))?,
+            ParseTree::Leaf { text, .. } => f.write_fmt(format_args!(
+                "{} {:?} {}\n",
+                self.symbol(),
+                text,
+                span_to_string(self.span())
+            ))?,
+        }
+        let children = self.children();
+        for (i, child) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            f.write_fmt(format_args!(
+                "{}{}",
+                prefix,
+                if last { "└── " } else { "├── " }
+            ))?;
+            child.fmt_indented(
+                f,
+                &format!("{}{}", prefix, if last { "    " } else { "│   " }),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn span_to_string(span: Option<Range<usize>>) -> String {
+    span.map_or(String::new(), |span| {
+        format!("{}..{}", span.start, span.end)
+    })
+}
+
+impl<'grammar> Display for ParseTree<'grammar> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_indented(f, "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::core::{Lexeme, Parser};
+    use crate::slr::builder::SLRTableBuilder;
+    use bnf::{Grammar, Production, Term};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <E'> ::= <E>
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= <T> '*' <F> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    /// every identifier is an 'id', everything else is a terminal named by itself
+    fn lex(source: &str) -> Vec<Lexeme> {
+        Lexeme::split_whitespace(source)
+            .into_iter()
+            .map(|lexeme| {
+                if lexeme.text.chars().all(char::is_alphanumeric) {
+                    Lexeme::new(Term::Terminal("id".to_string()), lexeme.text, lexeme.span)
+                } else {
+                    lexeme
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_works() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let parser = Parser::new(&slr);
+
+        let tree = parser.parse_tree(lex("a + b * c")).unwrap();
+        assert_eq!(tree.symbol(), &Term::from_str("<E>").unwrap());
+        assert_eq!(tree.span(), Some(0..9));
+        assert_eq!(tree.text(), "a + b * c");
+
+        let product = &tree.children()[2];
+        assert_eq!(product.symbol(), &Term::from_str("<T>").unwrap());
+        assert_eq!(product.span(), Some(4..9));
+        insta::assert_snapshot!(tree);
+    }
+
+    #[test]
+    fn tabled() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let parser = Parser::new(&slr);
+
+        let tree = parser.parse_tree(lex("( x + y ) * z")).unwrap();
+        assert_eq!(tree.span(), Some(0..13));
+        insta::assert_snapshot!(tree.tabled());
+    }
+
+    #[test]
+    fn without_span() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let parser = Parser::new(&slr);
+
+        let tokens = ["'id'", "'+'", "'id'"].map(|t| Term::from_str(t).unwrap());
+        let tree = parser.parse_tree(tokens).unwrap();
+        assert_eq!(tree.span(), None);
+        assert_eq!(tree.text(), "id + id");
+    }
+}
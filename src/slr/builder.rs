@@ -1,107 +1,96 @@
-use crate::lr0::core::{LR0Closure, LR0Item};
-use crate::slr::core::{SLRInstruction, SLRTable};
-use crate::slr::helper::IndexedGrammar;
+use crate::lr0::core::LR0Closure;
+use crate::slr::conflict::ActionCandidates;
+use crate::slr::core::SLRTable;
+use crate::slr::precedence::Precedence;
 
+use crate::error::Result;
+use crate::utils::first::First;
 use crate::utils::follow::Follow;
-use bnf::{Grammar, Production, Term};
-use std::cell::RefCell;
-use std::collections::HashMap;
-
-use log::{debug, info};
-use std::iter::repeat;
-
-pub struct SLRTableBuilder<'grammar> {
-    grammar: IndexedGrammar<'grammar>,
-    follow: Follow<'grammar>,
-    closure: LR0Closure<'grammar>,
-    table: RefCell<Vec<HashMap<&'grammar Term, SLRInstruction>>>,
+use bnf::{Grammar, Production};
+
+use log::info;
+
+pub struct SLRTableBuilder {
+    follow: Follow,
+    closure: LR0Closure,
+    precedence: Precedence,
 }
 
-impl<'grammar> SLRTableBuilder<'grammar> {
-    pub fn new(
-        grammar: &'grammar Grammar,
-        augmentation: &'grammar Production,
-    ) -> SLRTableBuilder<'grammar> {
-        let follow = Follow::new(grammar, &augmentation.lhs);
-        let closure = LR0Closure::new(grammar, augmentation);
-        let grammar = IndexedGrammar::new(grammar);
-        let table = RefCell::new(
-            repeat(HashMap::<&Term, SLRInstruction>::new())
-                .take(closure.len())
-                .collect(),
-        );
+impl SLRTableBuilder {
+    /// `augmentation` `<S'> ::= <S>` must be the only production of `<S'>` in `grammar`,
+    /// [`augment`](crate::utils::augmentation::augment) synthesizes one
+    ///
+    /// Panics if the grammar or the augmentation is invalid, see [`SLRTableBuilder::try_new`]
+    pub fn new(grammar: &Grammar, augmentation: &Production) -> SLRTableBuilder {
+        Self::try_new(grammar, augmentation).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as [`SLRTableBuilder::new`], but fail if the grammar or the augmentation is invalid
+    pub fn try_new(grammar: &Grammar, augmentation: &Production) -> Result<SLRTableBuilder> {
+        // validates the grammar and the augmentation
+        let closure = LR0Closure::try_new(grammar, augmentation)?;
+        let interned = &closure.grammar;
+        let first = First::new(interned);
+        let start = interned.production(closure.augmentation).lhs;
+        let follow = Follow::new(interned, &first, start);
         info!("LR0 Closure:\n{}", closure.closure_table());
         info!("LR0 Transition Table:\n{}", closure.transition_table());
-        SLRTableBuilder {
-            grammar,
+        Ok(SLRTableBuilder {
             follow,
             closure,
-            table,
-        }
+            precedence: Precedence::new(),
+        })
     }
 
-    fn shift(&self, from: usize, via: &'grammar Term) {
-        let to = self.closure.transition(from, via).unwrap(); // TODO: really not sure whether this unwrap is safe?
-        debug!("[SLR Builder] Shift: goto(I_{}, {}) = I_{}", from, via, to);
-        let mut table = self.table.borrow_mut();
-        table
-            .get_mut(from)
-            .unwrap()
-            .insert(via, SLRInstruction::Shift(to));
-    }
-
-    fn reduce(&self, index: usize, lr0: &LR0Item) {
-        let grammar_index = self.grammar.get_index_of(lr0.rhs).unwrap();
-        let prod = self.grammar.get(lr0.rhs).unwrap();
-        let mut table = self.table.borrow_mut();
-        for term in self.follow.follow_of(&prod.lhs).collect::<Vec<_>>() {
-            table[index].insert(term, SLRInstruction::Reduce(grammar_index));
-            debug!(
-                "[SLR Builder] Reduce: set (I_{}, {}) = r{}",
-                index, term, grammar_index
-            );
-        }
+    /// Resolve the shift/reduce conflicts by the precedence declarations
+    pub fn precedence(mut self, precedence: Precedence) -> SLRTableBuilder {
+        self.precedence = precedence;
+        self
     }
 
-    fn goto(&self, from: usize, via: &'grammar Term) {
-        debug_assert!(matches!(via, Term::Nonterminal(_)));
-        let to = self.closure.transition(from, via).unwrap(); // TODO: really not sure whether this unwrap is safe?
-        debug!("[SLR Builder] Goto: goto(I_{}, {}) = I_{}", from, via, to);
-        let mut table = self.table.borrow_mut();
-        table
-            .get_mut(from)
-            .unwrap()
-            .insert(via, SLRInstruction::Goto(to));
+    pub fn build(self) -> SLRTable {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
     }
 
-    pub fn build(self) -> SLRTable<'grammar> {
-        self.closure.enumerate_lr0().for_each(|(i, lr0)| {
-            // take a look at the char after the •
-            match lr0.expect() {
-                // lr0 expect non character, which is the form of A -> 𝛼 •
-                None => {
-                    /* Reduce */
-                    self.reduce(i, lr0)
-                }
-                Some(t) => {
-                    match t {
-                        Term::Terminal(_) => self.shift(i, t),   /* Shift */
-                        Term::Nonterminal(_) => self.goto(i, t), /* Goto */
-                    }
-                }
-            }
-        });
-        SLRTable {
-            grammar: self.grammar,
-            table: self.table.into_inner(),
-        }
+    /// Same as [`SLRTableBuilder::build`], but fail if a transition is missing from the automaton,
+    /// or a `%prec` names a production out of the grammar
+    pub fn try_build(self) -> Result<SLRTable> {
+        let grammar = &self.closure.grammar;
+        let mut actions = ActionCandidates::new();
+        actions.fill(
+            self.closure.enumerate_lr0(),
+            |from, via| self.closure.transition(from, via),
+            self.closure.augmentation,
+            grammar,
+            |_, _, item| {
+                self.follow
+                    .follow_of(item.lhs(grammar))
+                    .iter()
+                    .copied()
+                    .collect()
+            },
+        )?;
+        let (table, conflicts) = actions.resolve(self.closure.len(), grammar, &self.precedence)?;
+        Ok(SLRTable {
+            grammar: self.closure.grammar,
+            table,
+            conflicts,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::lalr::builder::LALRTableBuilder;
+    use crate::lr0::table::LR0TableBuilder;
+    use crate::lr1::table::LR1TableBuilder;
+    use crate::parser::core::{tokens, Parser};
     use crate::slr::builder::SLRTableBuilder;
-    use bnf::Production;
+    use crate::slr::conflict::ConflictKind;
+    use crate::slr::core::SLRInstruction;
+    use crate::utils::augmentation::augment;
+    use crate::utils::{dollar, epsilon};
+    use bnf::{Production, Term};
     use std::str::FromStr;
 
     #[test]
@@ -119,6 +108,171 @@ mod tests {
 
         let builder = SLRTableBuilder::new(&grammar, &augmentation);
         let slr = builder.build();
+        assert!(!slr.has_conflicts());
         insta::assert_snapshot!(slr);
     }
+
+    #[test]
+    fn synthesized_augmentation() {
+        let grammar = r#"
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= <T> '*' <F> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+        let start = Term::from_str("<E>").unwrap();
+        let (grammar, augmentation) = augment(&grammar, &start).unwrap();
+
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        assert!(!slr.has_conflicts());
+        let accepts = slr
+            .table
+            .iter()
+            .flat_map(|row| row.iter())
+            .filter(|(_, cell)| cell.chosen == SLRInstruction::Accept)
+            .collect::<Vec<_>>();
+        assert_eq!(accepts.len(), 1);
+        assert_eq!(slr.grammar().term(*accepts[0].0), dollar());
+    }
+
+    #[test]
+    #[should_panic(expected = "is not the only production of its lhs")]
+    fn augmentation_not_in_grammar() {
+        let grammar = r#"
+        <E> ::= <E> '+' 'id' | 'id'
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        SLRTableBuilder::new(&grammar, &augmentation);
+    }
+
+    #[test]
+    fn shift_reduce_conflict() {
+        let grammar = r#"
+        <S'> ::= <S>
+        <S> ::= <L> '=' <R> | <R>
+        <L> ::= '*' <R> | 'id'
+        <R> ::= <L>
+        "#
+        .parse()
+        .unwrap();
+
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        assert_eq!(slr.conflicts().len(), 1);
+        let conflict = &slr.conflicts()[0];
+        assert_eq!(conflict.kind(), ConflictKind::ShiftReduce);
+        assert_eq!(conflict.lookahead(), &Term::from_str("'='").unwrap());
+        assert!(matches!(conflict.chosen(), SLRInstruction::Shift(_)));
+        insta::assert_snapshot!(slr.conflict_table());
+    }
+
+    #[test]
+    fn reduce_reduce_conflict() {
+        let grammar = r#"
+        <S'> ::= <S>
+        <S> ::= <C> <A> | <B>
+        <A> ::= 'x'
+        <B> ::= <C> 'x'
+        <C> ::= 'c'
+        "#
+        .parse()
+        .unwrap();
+
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        assert_eq!(slr.conflicts().len(), 1);
+        let conflict = &slr.conflicts()[0];
+        assert_eq!(conflict.kind(), ConflictKind::ReduceReduce);
+        assert_eq!(conflict.items().len(), 2);
+        insta::assert_snapshot!(slr.conflict_table());
+    }
+
+    #[test]
+    fn identical_rhs() {
+        let grammar = r#"
+        <S'> ::= <S>
+        <S> ::= <A> 'x' | <B> 'y'
+        <A> ::= 'c'
+        <B> ::= 'c'
+        "#
+        .parse()
+        .unwrap();
+
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        assert!(!slr.has_conflicts());
+
+        let id = |term: &str| slr.grammar().id(&Term::from_str(term).unwrap()).unwrap();
+        let SLRInstruction::Shift(state) = slr.table[0][&id("'c'")].chosen else {
+            panic!("expect a shift on 'c'")
+        };
+        // <A> -> 'c' is r3, <B> -> 'c' is r4
+        assert_eq!(
+            slr.table[state][&id("'x'")].chosen,
+            SLRInstruction::Reduce(3)
+        );
+        assert_eq!(
+            slr.table[state][&id("'y'")].chosen,
+            SLRInstruction::Reduce(4)
+        );
+    }
+
+    #[test]
+    fn epsilon_production() {
+        let grammar = r#"
+        <S'> ::= <S>
+        <S> ::= 'a' <S> | 'ε'
+        "#
+        .parse()
+        .unwrap();
+
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let lalr = LALRTableBuilder::new(&grammar, &augmentation).build();
+        let lr1 = LR1TableBuilder::new(&grammar, &augmentation).build();
+        let lr0 = LR0TableBuilder::new(&grammar, &augmentation).build();
+        for table in [&slr, &lalr, &lr1, &lr0] {
+            // <S> -> 'ε' is reduced, never shifted
+            assert_eq!(table.grammar().id(epsilon()), None);
+            assert!(table.table[0][&table.grammar().end()]
+                .actions()
+                .contains(&SLRInstruction::Reduce(2)));
+        }
+        assert!(!slr.has_conflicts() && !lalr.has_conflicts() && !lr1.has_conflicts());
+        for input in ["", "a", "a a"] {
+            let expected = Parser::new(&slr).parse(tokens(input)).unwrap();
+            assert_eq!(Parser::new(&lalr).parse(tokens(input)).unwrap(), expected);
+            assert_eq!(Parser::new(&lr1).parse(tokens(input)).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn nullable_follow() {
+        let grammar = r#"
+        <S'> ::= <S>
+        <S> ::= <A> <B> 'c'
+        <A> ::= 'a'
+        <B> ::= 'ε' | 'b'
+        "#
+        .parse()
+        .unwrap();
+
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+
+        // 'c' follows <A> through the nullable <B>
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        let lalr = LALRTableBuilder::new(&grammar, &augmentation).build();
+        assert!(!slr.has_conflicts());
+        for input in ["a c", "a b c"] {
+            let expected = Parser::new(&lalr).parse(tokens(input)).unwrap();
+            assert_eq!(Parser::new(&slr).parse(tokens(input)).unwrap(), expected);
+        }
+    }
 }
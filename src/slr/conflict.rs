@@ -0,0 +1,333 @@
+use crate::error::{Error, Result};
+use crate::ir::grammar::{InternedGrammar, ProductionId};
+use crate::ir::symbol::SymbolId;
+use crate::lr0::core::LR0Item;
+use crate::slr::core::{ActionSet, SLRInstruction};
+use crate::slr::precedence::{Associativity, Precedence};
+use bnf::Term;
+use itertools::Itertools;
+use log::{debug, warn};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use tabled::builder::Builder;
+use tabled::Table;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ConflictKind {
+    ShiftReduce,
+    ReduceReduce,
+}
+
+impl Display for ConflictKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConflictKind::ShiftReduce => f.write_str("shift/reduce"),
+            ConflictKind::ReduceReduce => f.write_str("reduce/reduce"),
+        }
+    }
+}
+
+/// How the action of a conflicting cell is chosen
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Resolution {
+    /// the same as yacc, shift over reduce and the earliest production over the others
+    Default,
+    /// the lookahead and the production are at different precedence levels
+    Precedence,
+    /// the lookahead and the production are at the same precedence level
+    Associativity(Associativity),
+}
+
+impl Display for Resolution {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Resolution::Default => f.write_str("default"),
+            Resolution::Precedence => f.write_str("precedence"),
+            Resolution::Associativity(associativity) => associativity.fmt(f),
+        }
+    }
+}
+
+/// A cell of the parsing table that more than one action competes for
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub(crate) state: usize,
+    pub(crate) lookahead: Term,
+    /// all the distinct actions competing for the cell, sorted
+    pub(crate) actions: Vec<SLRInstruction>,
+    /// the items which give rise to the actions
+    pub(crate) items: Vec<LR0Item>,
+    /// the action that is finally written into the table
+    pub(crate) chosen: SLRInstruction,
+    pub(crate) resolution: Resolution,
+}
+
+impl Conflict {
+    pub fn state(&self) -> usize {
+        self.state
+    }
+
+    pub fn lookahead(&self) -> &Term {
+        &self.lookahead
+    }
+
+    pub fn actions(&self) -> &[SLRInstruction] {
+        &self.actions
+    }
+
+    pub fn items(&self) -> &[LR0Item] {
+        &self.items
+    }
+
+    pub fn chosen(&self) -> SLRInstruction {
+        self.chosen
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Whether the conflict is resolved by the precedence declarations
+    pub fn is_resolved(&self) -> bool {
+        self.resolution != Resolution::Default
+    }
+
+    pub fn kind(&self) -> ConflictKind {
+        if self
+            .actions
+            .iter()
+            .any(|action| matches!(action, SLRInstruction::Shift(_)))
+        {
+            ConflictKind::ShiftReduce
+        } else {
+            ConflictKind::ReduceReduce
+        }
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} conflict at (I_{}, {}): [{}]",
+            self.kind(),
+            self.state,
+            self.lookahead,
+            self.actions.iter().join(", ")
+        ))
+    }
+}
+
+/// The rows of a parsing table, and the conflicts found while filling them
+type Resolved = (Vec<BTreeMap<SymbolId, ActionSet>>, Vec<Conflict>);
+
+/// Collects every action proposed for each cell of a parsing table,
+/// together with the item giving rise to it
+#[derive(Default)]
+pub(crate) struct ActionCandidates {
+    cells: HashMap<(usize, SymbolId), Vec<(SLRInstruction, LR0Item)>>,
+}
+
+impl ActionCandidates {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `instruction` as a candidate of the cell (I_state, via)
+    pub(crate) fn insert(
+        &mut self,
+        state: usize,
+        via: SymbolId,
+        instruction: SLRInstruction,
+        item: &LR0Item,
+    ) {
+        self.cells
+            .entry((state, via))
+            .or_default()
+            .push((instruction, *item));
+    }
+
+    /// Propose the actions of every item `(state, item)` of an LR automaton,
+    /// whose goto function is `transition`: shift or goto over the term after the dot,
+    /// accept the completed augmentation on `$`, and reduce any other completed item
+    /// by its production on the terminals of `lookahead(state, production, item)`
+    pub(crate) fn fill<'a, T, L>(
+        &mut self,
+        items: impl IntoIterator<Item = (usize, &'a LR0Item)>,
+        transition: T,
+        augmentation: ProductionId,
+        grammar: &InternedGrammar,
+        lookahead: L,
+    ) -> Result<()>
+    where
+        T: Fn(usize, SymbolId) -> Option<usize>,
+        L: Fn(usize, ProductionId, &LR0Item) -> Vec<SymbolId>,
+    {
+        let start = grammar.production(augmentation).lhs;
+        for (state, item) in items {
+            let Some(via) = item.expect(grammar) else {
+                // the augmented start item <S'> -> <S> • accepts on $ only
+                if item.lhs(grammar) == start {
+                    debug!("[Table Builder] set (I_{}, $) = acc", state);
+                    self.insert(state, grammar.end(), SLRInstruction::Accept, item);
+                    continue;
+                }
+                for term in lookahead(state, item.production, item) {
+                    let instruction = SLRInstruction::Reduce(item.production.index());
+                    debug!(
+                        "[Table Builder] set (I_{}, {}) = {}",
+                        state,
+                        grammar.term(term),
+                        instruction
+                    );
+                    self.insert(state, term, instruction, item);
+                }
+                continue;
+            };
+            let to = transition(state, via).ok_or_else(|| Error::MissingTransition {
+                state,
+                term: grammar.term(via).clone(),
+            })?;
+            let instruction = match grammar.symbols().is_terminal(via) {
+                true => SLRInstruction::Shift(to),
+                false => SLRInstruction::Goto(to),
+            };
+            debug!(
+                "[Table Builder] set (I_{}, {}) = {}",
+                state,
+                grammar.term(via),
+                instruction
+            );
+            self.insert(state, via, instruction, item);
+        }
+        Ok(())
+    }
+
+    /// Fill a table of `states` rows with the candidates, every cell with more than one
+    /// distinct candidate is reported as a [`Conflict`] and resolved by `precedence`,
+    /// or by default, the cell keeps all of them for a generalized parser,
+    /// fail if `precedence` names a production out of `grammar`
+    pub(crate) fn resolve(
+        self,
+        states: usize,
+        grammar: &InternedGrammar,
+        precedence: &Precedence,
+    ) -> Result<Resolved> {
+        precedence.check(grammar)?;
+        let mut table = vec![BTreeMap::new(); states];
+        let mut conflicts = Vec::new();
+
+        // by the state, then the term, so that the conflicts are in the same order for any ids
+        for ((state, id), candidates) in self
+            .cells
+            .into_iter()
+            .sorted_by_key(|((state, id), _)| (*state, grammar.term(*id)))
+        {
+            let term = grammar.term(id);
+            let actions = candidates
+                .iter()
+                .map(|(action, _)| *action)
+                .unique()
+                .sorted()
+                .collect::<Vec<_>>();
+            let (chosen, resolution) = by_precedence(&actions, term, grammar, precedence)
+                .unwrap_or((resolve(&actions), Resolution::Default));
+
+            if actions.len() > 1 {
+                let items = candidates
+                    .into_iter()
+                    .map(|(_, item)| item)
+                    .unique()
+                    .sorted_by_key(|item| item.display(grammar))
+                    .collect::<Vec<_>>();
+                let conflict = Conflict {
+                    state,
+                    lookahead: term.clone(),
+                    actions: actions.clone(),
+                    items,
+                    chosen,
+                    resolution,
+                };
+                match resolution {
+                    Resolution::Default => warn!("{}, choose {}", conflict, chosen),
+                    _ => debug!("{}, choose {} by {}", conflict, chosen, resolution),
+                }
+                conflicts.push(conflict);
+            }
+
+            table[state].insert(id, ActionSet { actions, chosen });
+        }
+
+        Ok((table, conflicts))
+    }
+}
+
+/// The default resolution, same as yacc does:
+/// prefer shift over reduce, accept over any other reduce, and otherwise
+/// reduce by the production that comes first in the grammar
+pub(crate) fn resolve(actions: &[SLRInstruction]) -> SLRInstruction {
+    actions
+        .iter()
+        .find(|action| matches!(action, SLRInstruction::Shift(_)))
+        .or_else(|| {
+            actions
+                .iter()
+                .find(|action| matches!(action, SLRInstruction::Accept))
+        })
+        .or_else(|| {
+            actions
+                .iter()
+                .filter(|action| matches!(action, SLRInstruction::Reduce(_)))
+                .min()
+        })
+        .or_else(|| actions.first())
+        .copied()
+        .unwrap_or(SLRInstruction::Empty)
+}
+
+/// Resolve a shift/reduce conflict by the precedence of the lookahead and of the production,
+/// `None` if the cell is not exactly one shift against one reduce or either has no precedence,
+/// so that a reduce/reduce conflict is never hidden behind a shift
+fn by_precedence(
+    actions: &[SLRInstruction],
+    lookahead: &Term,
+    grammar: &InternedGrammar,
+    precedence: &Precedence,
+) -> Option<(SLRInstruction, Resolution)> {
+    let (shift, index) = match actions {
+        [SLRInstruction::Shift(to), SLRInstruction::Reduce(index)]
+        | [SLRInstruction::Reduce(index), SLRInstruction::Shift(to)] => {
+            (SLRInstruction::Shift(*to), *index)
+        }
+        _ => return None,
+    };
+    precedence.resolve(grammar, lookahead, shift, index)
+}
+
+pub(crate) fn conflict_table(grammar: &InternedGrammar, conflicts: &[Conflict]) -> Table {
+    let mut builder = Builder::default();
+    builder.push_record([
+        "State",
+        "Lookahead",
+        "Kind",
+        "Actions",
+        "Chosen",
+        "Resolution",
+        "Items",
+    ]);
+    for conflict in conflicts {
+        builder.push_record([
+            format!("I_{}", conflict.state),
+            conflict.lookahead.to_string(),
+            conflict.kind().to_string(),
+            conflict.actions.iter().join(", "),
+            conflict.chosen.to_string(),
+            conflict.resolution.to_string(),
+            conflict
+                .items
+                .iter()
+                .map(|item| item.display(grammar))
+                .join("\n"),
+        ]);
+    }
+    builder.build()
+}
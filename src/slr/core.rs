@@ -1,27 +1,30 @@
-use crate::slr::helper::IndexedGrammar;
-use crate::utils::dollar;
-use bnf::Term;
-use std::collections::HashMap;
+use crate::error::{Error, Result};
+use crate::ir::grammar::InternedGrammar;
+use crate::ir::symbol::SymbolId;
+use crate::slr::conflict::{conflict_table, Conflict};
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
 use std::iter::once;
 use tabled::builder::Builder;
 use tabled::Table;
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub enum SLRInstruction {
     Reduce(usize),
     Shift(usize),
     Goto(usize),
+    Accept,
     Empty,
 }
 
-impl ToString for SLRInstruction {
-    fn to_string(&self) -> String {
+impl Display for SLRInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            SLRInstruction::Reduce(i) => format!("r{}", i),
-            SLRInstruction::Shift(i) => format!("s{}", i),
-            SLRInstruction::Goto(i) => format!("g{}", i),
-            SLRInstruction::Empty => String::new(),
+            SLRInstruction::Reduce(i) => f.write_fmt(format_args!("r{}", i)),
+            SLRInstruction::Shift(i) => f.write_fmt(format_args!("s{}", i)),
+            SLRInstruction::Goto(i) => f.write_fmt(format_args!("g{}", i)),
+            SLRInstruction::Accept => f.write_str("acc"),
+            SLRInstruction::Empty => Ok(()),
         }
     }
 }
@@ -32,12 +35,60 @@ impl From<&SLRInstruction> for String {
     }
 }
 
-pub struct SLRTable<'grammar> {
-    pub(crate) grammar: IndexedGrammar<'grammar>,
-    pub(crate) table: Vec<HashMap<&'grammar Term, SLRInstruction>>,
+/// A cell of an LR parsing table, all the distinct actions proposed for it,
+/// more than one on a conflict
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ActionSet {
+    /// sorted
+    pub(crate) actions: Vec<SLRInstruction>,
+    /// the action taken by a deterministic parser, resolved on a conflict
+    pub(crate) chosen: SLRInstruction,
 }
 
-impl<'grammar> SLRTable<'grammar> {
+impl ActionSet {
+    pub fn actions(&self) -> &[SLRInstruction] {
+        &self.actions
+    }
+
+    pub fn chosen(&self) -> SLRInstruction {
+        self.chosen
+    }
+
+    pub fn is_conflict(&self) -> bool {
+        self.actions.len() > 1
+    }
+}
+
+/// An LR parsing table, the LR(0), SLR(1), LALR(1) and LR(1) builders all build one
+///
+/// The table owns its grammar, so that it can outlive the `bnf::Grammar` it is built from,
+/// `Reduce(index)` holds the index of a [`ProductionId`](crate::ir::grammar::ProductionId)
+#[derive(Debug, Clone)]
+pub struct SLRTable {
+    pub(crate) grammar: InternedGrammar,
+    pub(crate) table: Vec<BTreeMap<SymbolId, ActionSet>>,
+    pub(crate) conflicts: Vec<Conflict>,
+}
+
+impl SLRTable {
+    pub fn grammar(&self) -> &InternedGrammar {
+        &self.grammar
+    }
+
+    /// The number of states
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// The cell of the table in the row of `state` and the column of `symbol`, if any
+    pub fn cell(&self, state: usize, symbol: SymbolId) -> Option<&ActionSet> {
+        self.table.get(state)?.get(&symbol)
+    }
+
     pub fn grammar_table(&self) -> Table {
         self.grammar.grammar_table()
     }
@@ -48,27 +99,158 @@ impl<'grammar> SLRTable<'grammar> {
         let header = self
             .grammar
             .terminals()
-            .chain(once(dollar()))
-            .chain(self.grammar.non_terminals())
+            .chain(once(self.grammar.end()))
+            .chain(self.grammar.nonterminals())
             .collect::<Vec<_>>();
 
-        builder.push_record(header.iter().map(|t| t.to_string()));
+        builder.push_record(header.iter().map(|id| self.grammar.term(*id).to_string()));
 
-        self.table.iter().enumerate().for_each(|(_, table)| {
+        self.table.iter().for_each(|row| {
             let row = header
                 .iter()
-                .map(|t| table.get(t).unwrap_or(&SLRInstruction::Empty))
+                .map(|id| {
+                    row.get(id)
+                        .map_or(&SLRInstruction::Empty, |cell| &cell.chosen)
+                })
                 .collect::<Vec<_>>();
             builder.push_record(row);
         });
 
         builder.index().build()
     }
+
+    /// All the conflicts found while building the table, including the ones resolved
+    /// by the precedence declarations, the grammar is in the class of the builder,
+    /// e.g. SLR(1) for `SLRTableBuilder`, if and only if there is none
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    /// Whether some conflict is left to the default resolution
+    pub fn has_conflicts(&self) -> bool {
+        self.conflicts
+            .iter()
+            .any(|conflict| !conflict.is_resolved())
+    }
+
+    /// Fail with the first conflict left to the default resolution, if any
+    pub fn check_conflicts(&self) -> Result<()> {
+        match self
+            .conflicts
+            .iter()
+            .find(|conflict| !conflict.is_resolved())
+        {
+            Some(conflict) => Err(Error::from(conflict)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn conflict_table(&self) -> Table {
+        conflict_table(&self.grammar, &self.conflicts)
+    }
 }
 
-impl<'grammar> Display for SLRTable<'grammar> {
+impl Display for SLRTable {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!("Grammar: \n{}\n", self.grammar_table()))?;
-        f.write_fmt(format_args!("Table: \n{}", self.parsing_table()))
+        f.write_fmt(format_args!("Table: \n{}", self.parsing_table()))?;
+        if !self.conflicts.is_empty() {
+            f.write_fmt(format_args!("\nConflicts: \n{}", self.conflict_table()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::lr1::table::LR1TableBuilder;
+    use crate::parser::core::{tokens, Parser};
+    use crate::parser::table::LRTable;
+    use crate::slr::builder::SLRTableBuilder;
+    use crate::slr::core::SLRInstruction;
+    use crate::utils::{dollar, epsilon};
+    use bnf::{Grammar, Production, Term};
+    use std::str::FromStr;
+
+    #[test]
+    fn owned() {
+        let slr = {
+            let grammar: Grammar = r#"
+            <E'> ::= <E>
+            <E> ::= <E> '+' <T> | <T>
+            <T> ::= <T> '*' <F> | <F>
+            <F> ::= '(' <E> ')' | 'id'
+            "#
+            .parse()
+            .unwrap();
+            let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+            SLRTableBuilder::new(&grammar, &augmentation).build()
+        };
+
+        // the table outlives the grammar and can be sent to another thread
+        let handle = std::thread::spawn(move || {
+            let id = Term::from_str("'id'").unwrap();
+            slr.action(0, &id)
+        });
+        assert_eq!(handle.join().unwrap(), SLRInstruction::Shift(2));
+    }
+
+    #[test]
+    fn lr1_table() {
+        let grammar: Grammar = r#"
+        <E'> ::= <E>
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= <T> '*' <F> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let lr1 = LR1TableBuilder::new(&grammar, &augmentation).build();
+        assert_eq!(lr1.len(), 22);
+
+        let e = Term::from_str("<E>").unwrap();
+        assert!(lr1.goto(0, &e).is_some());
+    }
+
+    #[test]
+    fn epsilon_column() {
+        let grammar: Grammar = r#"
+        <S'> ::= <S>
+        <S> ::= 'a' <S> | 'ε'
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        // 'ε' is an empty rhs, not a column, and stays apart from '$'
+        assert_eq!(slr.grammar().id(epsilon()), None);
+        let end = slr.grammar().end();
+        assert_eq!(slr.grammar().term(end), dollar());
+        assert_eq!(
+            slr.cell(0, end).map(|cell| cell.chosen()),
+            Some(SLRInstruction::Reduce(2))
+        );
+
+        let parser = Parser::new(&slr);
+        assert_eq!(parser.parse(tokens("")), Ok(vec![2]));
+        assert_eq!(parser.parse(tokens("a a")), Ok(vec![2, 1, 1]));
+        assert!(parser.parse(tokens("a b")).is_err());
+    }
+
+    #[test]
+    fn reserved_terminal() {
+        let grammar: Grammar = r#"
+        <S'> ::= <S>
+        <S> ::= 'a' '$'
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+        assert_eq!(
+            SLRTableBuilder::try_new(&grammar, &augmentation).err(),
+            Some(Error::ReservedTerminal(dollar().clone()))
+        );
     }
 }
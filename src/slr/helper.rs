@@ -1,59 +1,43 @@
-use bnf::{Expression, Grammar, Production, Term};
+use bnf::{Expression, Grammar, Term};
 use indexmap::IndexMap;
-use itertools::Itertools;
-use tabled::builder::Builder;
-use tabled::Table;
+use std::collections::HashMap;
 
 pub struct IndexedGrammar<'grammar> {
-    /// rhs -> lhs
-    grammar: IndexMap<&'grammar Expression, &'grammar Term>,
-    /// original
-    original: &'grammar Grammar,
+    /// (lhs, the index of the alternative among all the alternatives of lhs) -> rhs,
+    /// the index in the map is the index of the production
+    grammar: IndexMap<(&'grammar Term, usize), &'grammar Expression>,
 }
 
 impl<'grammar> IndexedGrammar<'grammar> {
     pub fn new(grammar: &'grammar Grammar) -> IndexedGrammar<'grammar> {
-        let original = grammar;
+        let mut alternatives = HashMap::<&Term, usize>::new();
         let grammar = grammar
             .productions_iter()
-            .flat_map(|production| production.rhs_iter().map(|expr| (expr, &production.lhs)))
-            .collect::<IndexMap<&Expression, &Term>>();
-
-        IndexedGrammar { grammar, original }
-    }
-
-    pub fn grammar_table(&self) -> Table {
-        let mut builder = Builder::default();
-        builder.push_record(["Rule"]);
-        self.grammar.iter().for_each(|(rhs, lhs)| {
-            builder.push_record([format!("{} -> {}", lhs, rhs)]);
-        });
-        builder.index().build()
-    }
-
-    pub fn get_index_of(&self, expr: &Expression) -> Option<usize> {
-        self.grammar.get_index_of(expr)
+            .flat_map(|production| production.rhs_iter().map(|expr| (&production.lhs, expr)))
+            .map(|(lhs, rhs)| {
+                let alternative = alternatives.entry(lhs).or_default();
+                *alternative += 1;
+                ((lhs, *alternative - 1), rhs)
+            })
+            .collect::<IndexMap<(&Term, usize), &Expression>>();
+
+        IndexedGrammar { grammar }
     }
 
-    pub(crate) fn get(&self, expr: &Expression) -> Option<Production> {
-        let lhs = self.grammar.get(expr)?;
-        Some(Production::from_parts(Term::clone(lhs), vec![expr.clone()]))
+    /// The production indexed by `index`, as `(lhs, rhs)`
+    pub(crate) fn get_index(&self, index: usize) -> Option<(&'grammar Term, &'grammar Expression)> {
+        self.grammar
+            .get_index(index)
+            .map(|((lhs, _), rhs)| (*lhs, *rhs))
     }
 
-    pub(crate) fn non_terminals<'a>(&'a self) -> impl Iterator<Item = &'grammar Term> {
-        self.original
-            .productions_iter()
-            .map(|prod| &prod.lhs)
-            .filter(|term| matches!(*term, Term::Nonterminal(_)))
-            .unique()
-    }
-
-    pub(crate) fn terminals<'a>(&'a self) -> impl Iterator<Item = &'grammar Term> {
-        self.original
-            .productions_iter()
-            .flat_map(|prod| prod.rhs_iter())
-            .flat_map(|expr| expr.terms_iter())
-            .filter(|term| matches!(*term, Term::Terminal(_)))
-            .unique()
+    /// All the productions as `(index, (lhs, rhs))`
+    pub(crate) fn iter(
+        &self,
+    ) -> impl Iterator<Item = (usize, (&'grammar Term, &'grammar Expression))> + '_ {
+        self.grammar
+            .iter()
+            .enumerate()
+            .map(|(index, ((lhs, _), rhs))| (index, (*lhs, *rhs)))
     }
 }
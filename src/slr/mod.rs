@@ -1,3 +1,5 @@
 pub mod builder;
+pub mod conflict;
 pub mod core;
-mod helper;
+pub(crate) mod helper;
+pub mod precedence;
@@ -0,0 +1,350 @@
+use crate::error::{Error, Result};
+use crate::ir::grammar::{InternedGrammar, ProductionId};
+use crate::slr::conflict::Resolution;
+use crate::slr::core::SLRInstruction;
+use bnf::Term;
+use itertools::Itertools;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// How the operators of the same precedence level group
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Associativity {
+    /// `%left`, `a + b + c` is `(a + b) + c`
+    Left,
+    /// `%right`, `a ^ b ^ c` is `a ^ (b ^ c)`
+    Right,
+    /// `%nonassoc`, `a < b < c` is a syntax error
+    NonAssoc,
+}
+
+impl Display for Associativity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Associativity::Left => f.write_str("%left"),
+            Associativity::Right => f.write_str("%right"),
+            Associativity::NonAssoc => f.write_str("%nonassoc"),
+        }
+    }
+}
+
+/// yacc-style precedence declarations, which resolve the shift/reduce conflicts
+/// of an LR table, every level binds tighter than the ones declared before
+///
+/// A production takes the precedence of the last terminal of its rhs, unless overridden by `%prec`
+#[derive(Debug, Clone, Default)]
+pub struct Precedence {
+    /// terminal -> (level, associativity), the levels start from 1
+    terminals: HashMap<Term, (usize, Associativity)>,
+    /// (lhs, the index of the alternative among all the alternatives of lhs)
+    /// -> the terminal given by `%prec`
+    overrides: HashMap<(Term, usize), Term>,
+    levels: usize,
+}
+
+impl Precedence {
+    pub fn new() -> Precedence {
+        Self::default()
+    }
+
+    /// `%left`, a new level of left associative terminals
+    pub fn left(self, terminals: impl IntoIterator<Item = Term>) -> Precedence {
+        self.level(Associativity::Left, terminals)
+    }
+
+    /// `%right`, a new level of right associative terminals
+    pub fn right(self, terminals: impl IntoIterator<Item = Term>) -> Precedence {
+        self.level(Associativity::Right, terminals)
+    }
+
+    /// `%nonassoc`, a new level of non-associative terminals
+    pub fn nonassoc(self, terminals: impl IntoIterator<Item = Term>) -> Precedence {
+        self.level(Associativity::NonAssoc, terminals)
+    }
+
+    /// A new level of `associativity`, a terminal declared again moves to this level
+    pub fn level(
+        mut self,
+        associativity: Associativity,
+        terminals: impl IntoIterator<Item = Term>,
+    ) -> Precedence {
+        self.levels += 1;
+        for terminal in terminals {
+            self.terminals
+                .insert(terminal, (self.levels, associativity));
+        }
+        self
+    }
+
+    /// `%prec`, the `alternative`-th alternative of `lhs` takes the precedence of `terminal`,
+    /// which may be a pseudo terminal out of the grammar, e.g. `UMINUS`
+    pub fn prec(mut self, lhs: Term, alternative: usize, terminal: Term) -> Precedence {
+        self.overrides.insert((lhs, alternative), terminal);
+        self
+    }
+
+    /// The level and the associativity of `terminal`, if declared
+    pub fn terminal(&self, terminal: &Term) -> Option<(usize, Associativity)> {
+        self.terminals.get(terminal).copied()
+    }
+
+    /// The level and the associativity of the production `index`, if any
+    pub(crate) fn production(
+        &self,
+        grammar: &InternedGrammar,
+        index: usize,
+    ) -> Option<(usize, Associativity)> {
+        let id = ProductionId(index as u32);
+        let production = grammar.productions.get(index)?;
+        let lhs = grammar.term(production.lhs);
+        let terminal = match self
+            .overrides
+            .get(&(lhs.clone(), grammar.alternative_of(id)))
+        {
+            Some(terminal) => terminal,
+            None => grammar.term(
+                *production
+                    .rhs
+                    .iter()
+                    .rev()
+                    .find(|symbol| grammar.symbols().is_terminal(**symbol))?,
+            ),
+        };
+        self.terminal(terminal)
+    }
+
+    /// Fail if a `%prec` names a production out of `grammar`
+    pub(crate) fn check(&self, grammar: &InternedGrammar) -> Result<()> {
+        match self
+            .overrides
+            .keys()
+            .sorted()
+            .find(|(lhs, alternative)| grammar.alternative(lhs, *alternative).is_none())
+        {
+            Some((lhs, alternative)) => Err(Error::UndefinedProduction {
+                lhs: lhs.clone(),
+                alternative: *alternative,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Resolve a conflict between shifting `lookahead` and reducing by the production `index`,
+    /// `None` if either has no precedence
+    pub(crate) fn resolve(
+        &self,
+        grammar: &InternedGrammar,
+        lookahead: &Term,
+        shift: SLRInstruction,
+        index: usize,
+    ) -> Option<(SLRInstruction, Resolution)> {
+        let (token, associativity) = self.terminal(lookahead)?;
+        let (rule, _) = self.production(grammar, index)?;
+        let reduce = SLRInstruction::Reduce(index);
+        Some(match token.cmp(&rule) {
+            Ordering::Greater => (shift, Resolution::Precedence),
+            Ordering::Less => (reduce, Resolution::Precedence),
+            Ordering::Equal => {
+                let chosen = match associativity {
+                    Associativity::Left => reduce,
+                    Associativity::Right => shift,
+                    Associativity::NonAssoc => SLRInstruction::Empty,
+                };
+                (chosen, Resolution::Associativity(associativity))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::lalr::builder::LALRTableBuilder;
+    use crate::lr1::table::LR1TableBuilder;
+    use crate::parser::core::{tokens, Parser};
+    use crate::slr::builder::SLRTableBuilder;
+    use crate::slr::conflict::Resolution;
+    use crate::slr::core::SLRInstruction;
+    use crate::slr::precedence::{Associativity, Precedence};
+    use bnf::{Grammar, Production, Term};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <E'> ::= <E>
+        <E> ::= <E> '+' <E> | <E> '-' <E> | <E> '*' <E> | <E> '^' <E> | '-' <E> | '(' <E> ')' | 'id'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    fn terminals(terminals: &[&str]) -> Vec<Term> {
+        terminals
+            .iter()
+            .map(|t| Term::Terminal(t.to_string()))
+            .collect()
+    }
+
+    fn precedence() -> Precedence {
+        Precedence::new()
+            .left(terminals(&["+", "-"]))
+            .left(terminals(&["*"]))
+            .right(terminals(&["^"]))
+            .right(terminals(&["UMINUS"]))
+            // <E> -> '-' <E>
+            .prec(
+                Term::from_str("<E>").unwrap(),
+                4,
+                Term::Terminal("UMINUS".to_string()),
+            )
+    }
+
+    #[test]
+    fn it_works() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation)
+            .precedence(precedence())
+            .build();
+
+        // every conflict is still reported, but none is left to the default
+        assert!(!slr.conflicts().is_empty());
+        assert!(slr
+            .conflicts()
+            .iter()
+            .all(|conflict| conflict.is_resolved()));
+        assert!(!slr.has_conflicts());
+        assert_eq!(slr.check_conflicts(), Ok(()));
+        insta::assert_snapshot!(slr.conflict_table());
+
+        let parser = Parser::new(&slr);
+        // ((id - id) - (id * (id ^ (id ^ id))))
+        let tree = parser
+            .parse_tree(tokens("id - id - id * id ^ id ^ id"))
+            .unwrap();
+        insta::assert_snapshot!(tree);
+
+        // (- id) * id
+        let tree = parser.parse_tree(tokens("- id * id")).unwrap();
+        assert_eq!(tree.production(), Some(3));
+        assert_eq!(tree.children()[0].production(), Some(5));
+    }
+
+    #[test]
+    fn nonassoc() {
+        let grammar: Grammar = r#"
+        <E'> ::= <E>
+        <E> ::= <E> '<' <E> | 'id'
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation)
+            .precedence(Precedence::new().nonassoc(terminals(&["<"])))
+            .build();
+
+        let conflict = &slr.conflicts()[0];
+        assert_eq!(
+            conflict.resolution(),
+            Resolution::Associativity(Associativity::NonAssoc)
+        );
+        assert_eq!(conflict.chosen(), SLRInstruction::Empty);
+
+        let parser = Parser::new(&slr);
+        assert!(parser.parse(tokens("id < id")).is_ok());
+        let error = parser.parse(tokens("id < id < id")).unwrap_err();
+        assert_eq!(error.position(), 3);
+    }
+
+    #[test]
+    fn undefined_production() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let e = Term::from_str("<E>").unwrap();
+        let uminus = Term::Terminal("UMINUS".to_string());
+        let precedence = Precedence::new().prec(e.clone(), 7, uminus);
+        let error = SLRTableBuilder::new(&grammar, &augmentation)
+            .precedence(precedence.clone())
+            .try_build()
+            .err();
+        let expected = Error::UndefinedProduction {
+            lhs: e,
+            alternative: 7,
+        };
+        assert_eq!(error, Some(expected.clone()));
+        let error = LR1TableBuilder::new(&grammar, &augmentation)
+            .precedence(precedence)
+            .try_build()
+            .err();
+        assert_eq!(error, Some(expected));
+    }
+
+    #[test]
+    fn reduce_reduce() {
+        let grammar: Grammar = r#"
+        <E'> ::= <E>
+        <E> ::= <E> '+' <E> | <T> | 'id'
+        <T> ::= <E> '+' <E>
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation)
+            .precedence(Precedence::new().left(terminals(&["+"])))
+            .build();
+
+        // a shift against two reduces is left to the default, so is reported
+        let plus = Term::from_str("'+'").unwrap();
+        let conflict = slr
+            .conflicts()
+            .iter()
+            .find(|conflict| conflict.lookahead() == &plus && conflict.actions().len() == 3)
+            .unwrap();
+        assert_eq!(conflict.resolution(), Resolution::Default);
+        assert!(slr.has_conflicts());
+        assert!(slr.check_conflicts().is_err());
+    }
+
+    #[test]
+    fn tables() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<E'> ::= <E>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation)
+            .precedence(precedence())
+            .build();
+        let lalr = LALRTableBuilder::new(&grammar, &augmentation)
+            .precedence(precedence())
+            .build();
+        let lr1 = LR1TableBuilder::new(&grammar, &augmentation)
+            .precedence(precedence())
+            .build();
+        assert!(!lalr.has_conflicts());
+        assert!(!lr1.has_conflicts());
+
+        for input in [
+            "id + id * id",
+            "id * id + id",
+            "- id ^ id - id",
+            "( id + id ) * - id",
+            "id ^ id ^ id",
+        ] {
+            let expected = Parser::new(&slr).parse(tokens(input)).unwrap();
+            assert_eq!(Parser::new(&lalr).parse(tokens(input)).unwrap(), expected);
+            assert_eq!(Parser::new(&lr1).parse(tokens(input)).unwrap(), expected);
+        }
+
+        // an undeclared terminal is left to the default
+        let lr1 = LR1TableBuilder::new(&grammar, &augmentation)
+            .precedence(Precedence::new().left(terminals(&["+", "-"])))
+            .build();
+        assert!(lr1.has_conflicts());
+        let star = Term::from_str("'*'").unwrap();
+        assert!(lr1
+            .conflicts()
+            .iter()
+            .filter(|conflict| conflict.lookahead() == &star)
+            .all(|conflict| conflict.resolution() == Resolution::Default
+                && matches!(conflict.chosen(), SLRInstruction::Shift(_))));
+    }
+}
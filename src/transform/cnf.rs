@@ -0,0 +1,572 @@
+use crate::parser::tree::ParseTree;
+use crate::slr::helper::IndexedGrammar;
+use crate::transform::epsilon::EpsilonElimination;
+use crate::transform::unit::UnitElimination;
+use crate::transform::useless::UselessElimination;
+use crate::transform::{alternatives, to_grammar, Alternatives};
+use crate::utils::{epsilon, fresh_nonterminal};
+use bnf::{Expression, Grammar, Term};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use log::debug;
+use std::fmt::{Display, Formatter};
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// What a nonterminal introduced by [`to_cnf`] stands for
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CnfOrigin {
+    /// `T_a -> a`, a terminal lifted out of a long right-hand side
+    Terminal(Term),
+    /// the suffix starting at `position` of the alternative `production` of the simplified grammar
+    Suffix { production: usize, position: usize },
+}
+
+/// A grammar in Chomsky Normal Form, where every alternative is `A -> B C` or `A -> a`,
+/// and only the start symbol may derive ε, by `S -> 'ε'`
+///
+/// The conversion first simplifies the grammar by eliminating ε-productions,
+/// unit productions and useless symbols, then lifts terminals and binarizes what is left,
+/// every CNF alternative is mapped back to the alternative of the simplified grammar
+/// it comes from
+pub struct Cnf {
+    pub(crate) grammar: Grammar,
+    /// the grammar before terminal lifting and binarization, one production per nonterminal
+    pub(crate) simplified: Grammar,
+    /// the alternatives of `simplified` as `(lhs, rhs)`, by the index of the production
+    pub(crate) productions: Vec<(Term, Expression)>,
+    /// the fresh nonterminals, mapped to what they stand for
+    pub(crate) origins: IndexMap<Term, CnfOrigin>,
+    /// for every alternative of `grammar`, the alternative of `simplified` it comes from,
+    /// `None` for `T_a -> a`
+    pub(crate) sources: Vec<Option<usize>>,
+}
+
+/// Convert `grammar` into Chomsky Normal Form, the lhs of the first production is the start symbol
+pub fn to_cnf(grammar: &Grammar) -> Cnf {
+    let grammar = EpsilonElimination::eliminate(grammar).into_grammar();
+    let grammar = UnitElimination::eliminate(&grammar).into_grammar();
+    let grammar = UselessElimination::eliminate(&grammar).into_grammar();
+    CnfBuilder::new(&grammar).build()
+}
+
+/// Whether every alternative of `grammar` is `A -> B C` or `A -> a`,
+/// or `S -> 'ε'` where `S` is the start symbol and is used on no right-hand side
+pub fn is_cnf(grammar: &Grammar) -> bool {
+    let start = grammar.productions_iter().next().map(|p| &p.lhs);
+    let start_on_rhs = grammar
+        .productions_iter()
+        .flat_map(|p| p.rhs_iter())
+        .flat_map(|expr| expr.terms_iter())
+        .any(|term| Some(term) == start);
+    grammar.productions_iter().all(|production| {
+        production.rhs_iter().all(
+            |expr| match expr.terms_iter().collect::<Vec<_>>().as_slice() {
+                [Term::Nonterminal(_), Term::Nonterminal(_)] => true,
+                [epsilon @ Term::Terminal(_)] if *epsilon == self::epsilon() => {
+                    Some(&production.lhs) == start && !start_on_rhs
+                }
+                [Term::Terminal(_)] => true,
+                _ => false,
+            },
+        )
+    })
+}
+
+impl Cnf {
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    pub fn into_grammar(self) -> Grammar {
+        self.grammar
+    }
+
+    /// The ε-free, unit-free and reduced grammar the CNF is built from
+    pub fn simplified(&self) -> &Grammar {
+        &self.simplified
+    }
+
+    pub fn origins(&self) -> &IndexMap<Term, CnfOrigin> {
+        &self.origins
+    }
+
+    /// What `term` stands for, `None` if it is not fresh
+    pub fn origin(&self, term: &Term) -> Option<&CnfOrigin> {
+        self.origins.get(term)
+    }
+
+    /// The alternative of the simplified grammar the CNF alternative `index` comes from
+    pub fn source(&self, index: usize) -> Option<usize> {
+        self.sources.get(index).copied().flatten()
+    }
+
+    /// Rebuild a parse tree of the simplified grammar from a parse tree of the CNF grammar,
+    /// by splicing the fresh nonterminals back into their parents,
+    /// `None` if `tree` is not a parse tree of the CNF grammar
+    pub fn unbinarize<'a>(&'a self, tree: &ParseTree<'_>) -> Option<ParseTree<'a>> {
+        let (lhs, children) = match tree {
+            ParseTree::Node { lhs, children, .. } => (lhs, children),
+            // a leaf is not tied to the grammar
+            ParseTree::Leaf {
+                terminal,
+                text,
+                span,
+            } => {
+                return Some(ParseTree::Leaf {
+                    terminal: terminal.clone(),
+                    text: text.clone(),
+                    span: span.clone(),
+                })
+            }
+        };
+        let children = self
+            .splice(children)
+            .into_iter()
+            .map(|child| self.unbinarize(child))
+            .collect::<Option<Vec<_>>>()?;
+        let symbols = children.iter().map(|child| child.symbol()).collect_vec();
+
+        let (index, (lhs, rhs)) = self.productions.iter().enumerate().find(|(_, (l, rhs))| {
+            l == *lhs
+                && rhs
+                    .terms_iter()
+                    .filter(|term| *term != epsilon())
+                    .eq(symbols.iter().copied())
+        })?;
+        Some(ParseTree::node(lhs, rhs, index, children))
+    }
+
+    /// Rebuild a parse tree of `original`, the grammar the CNF is converted from,
+    /// from a parse tree of the simplified grammar, by inserting back
+    /// the unit productions and the derivations of ε removed by the simplification,
+    /// `None` if `tree` is not a parse tree of the simplified grammar
+    pub fn restore<'g>(
+        &self,
+        original: &'g Grammar,
+        tree: &ParseTree<'_>,
+    ) -> Option<ParseTree<'g>> {
+        Restorer::new(original)?.restore(tree)
+    }
+
+    /// The children of a node, with the fresh nonterminals replaced by their own children
+    fn splice<'t, 'g>(&self, children: &'t [ParseTree<'g>]) -> Vec<&'t ParseTree<'g>> {
+        children
+            .iter()
+            .flat_map(|child| match child {
+                ParseTree::Node { lhs, children, .. } if self.origins.contains_key(*lhs) => {
+                    self.splice(children)
+                }
+                _ => vec![child],
+            })
+            .collect()
+    }
+
+    pub fn origin_table(&self) -> Table {
+        let mut builder = Builder::default();
+        builder.push_record(["Nonterminal", "Origin"]); // header
+        for (fresh, origin) in self.origins.iter() {
+            let origin = match origin {
+                CnfOrigin::Terminal(terminal) => terminal.to_string(),
+                CnfOrigin::Suffix {
+                    production,
+                    position,
+                } => {
+                    let (lhs, rhs) = &self.productions[*production];
+                    format!(
+                        "{} of r{}: {} -> {}",
+                        rhs.terms_iter().skip(*position).join(" "),
+                        production,
+                        lhs,
+                        rhs
+                    )
+                }
+            };
+            builder.push_record([fresh.to_string(), origin]);
+        }
+        builder.build()
+    }
+}
+
+impl Display for Cnf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Grammar: \n{}", self.grammar))?;
+        f.write_fmt(format_args!("Origins: \n{}", self.origin_table()))
+    }
+}
+
+/// Which alternatives of the original grammar a simplified node comes from
+pub(crate) struct Restorer<'g> {
+    grammar: IndexedGrammar<'g>,
+    start: &'g Term,
+    /// the nullable nonterminals, each with an alternative deriving ε,
+    /// in an order where every alternative only uses nullable nonterminals before it
+    nullable: IndexMap<&'g Term, usize>,
+}
+
+impl<'g> Restorer<'g> {
+    /// `None` if `original` has no production
+    pub(crate) fn new(original: &'g Grammar) -> Option<Restorer<'g>> {
+        let grammar = IndexedGrammar::new(original);
+        let start = &original.productions_iter().next()?.lhs;
+        let mut nullable = IndexMap::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, (lhs, rhs)) in grammar.iter() {
+                if !nullable.contains_key(lhs)
+                    && rhs
+                        .terms_iter()
+                        .all(|term| term == epsilon() || nullable.contains_key(term))
+                {
+                    nullable.insert(lhs, index);
+                    changed = true;
+                }
+            }
+        }
+        Some(Restorer {
+            grammar,
+            start,
+            nullable,
+        })
+    }
+
+    /// The terms of `rhs` without `'ε'`
+    fn terms(rhs: &'g Expression) -> Vec<&'g Term> {
+        rhs.terms_iter().filter(|term| *term != epsilon()).collect()
+    }
+
+    /// Which terms of `alternative` to keep so that it becomes `symbols`,
+    /// only nullable nonterminals may be dropped
+    fn matches(&self, alternative: &[&Term], symbols: &[&Term]) -> Option<Vec<bool>> {
+        let Some((head, rest)) = alternative.split_first() else {
+            return symbols.is_empty().then(Vec::new);
+        };
+        let kept = match symbols.split_first() {
+            Some((symbol, symbols)) if symbol == head => self.matches(rest, symbols),
+            _ => None,
+        };
+        kept.map(|plan| [true].into_iter().chain(plan).collect())
+            .or_else(|| {
+                self.nullable.contains_key(head).then(|| {
+                    self.matches(rest, symbols)
+                        .map(|plan| [false].into_iter().chain(plan).collect())
+                })?
+            })
+    }
+
+    /// The node `index` whose dropped terms derive ε, and whose kept terms are `children`
+    fn node(
+        &self,
+        index: usize,
+        plan: &[bool],
+        children: Vec<ParseTree<'g>>,
+    ) -> Option<ParseTree<'g>> {
+        let (lhs, rhs) = self.grammar.get_index(index)?;
+        let mut children = children.into_iter();
+        let children = Self::terms(rhs)
+            .into_iter()
+            .zip(plan)
+            .map(|(term, kept)| match kept {
+                true => children.next(),
+                false => self.epsilon_tree(term),
+            })
+            .collect::<Option<_>>()?;
+        Some(ParseTree::node(lhs, rhs, index, children))
+    }
+
+    /// A derivation of ε from `term`, `None` if it is not nullable
+    fn epsilon_tree(&self, term: &Term) -> Option<ParseTree<'g>> {
+        let index = *self.nullable.get(term)?;
+        let (_, rhs) = self.grammar.get_index(index)?;
+        self.node(index, &vec![false; Self::terms(rhs).len()], vec![])
+    }
+
+    /// The parse tree of the original grammar for the parse tree `tree` of the simplified one,
+    /// `None` if there is none
+    pub(crate) fn restore(&self, tree: &ParseTree<'_>) -> Option<ParseTree<'g>> {
+        let (lhs, children) = match tree {
+            ParseTree::Node { lhs, children, .. } => (*lhs, children),
+            ParseTree::Leaf {
+                terminal,
+                text,
+                span,
+            } => {
+                return Some(ParseTree::Leaf {
+                    terminal: terminal.clone(),
+                    text: text.clone(),
+                    span: span.clone(),
+                })
+            }
+        };
+        let children = children
+            .iter()
+            .map(|child| self.restore(child))
+            .collect::<Option<Vec<_>>>()?;
+        let symbols = children.iter().map(|child| child.symbol()).collect_vec();
+
+        // the fresh start symbol added for ε stands for the original one
+        let lhs = match self.grammar.iter().any(|(_, (l, _))| l == lhs) {
+            true => lhs,
+            false if children.is_empty() => return self.epsilon_tree(self.start),
+            false => self.start,
+        };
+
+        // look for A -> B_1 -> ... -> B_k -> symbols, where every step drops nullable terms only
+        let mut parents = IndexMap::<&Term, Option<(&Term, usize, Vec<bool>)>>::new();
+        parents.insert(lhs, None);
+        let mut i = 0;
+        while let Some((&n, _)) = parents.get_index(i) {
+            for (index, (_, rhs)) in self.grammar.iter().filter(|(_, (l, _))| *l == n) {
+                let terms = Self::terms(rhs);
+                if let Some(plan) = self.matches(&terms, &symbols) {
+                    let mut tree = self.node(index, &plan, children)?;
+                    let mut step = &parents[n];
+                    while let Some((parent, index, plan)) = step {
+                        tree = self.node(*index, plan, vec![tree])?;
+                        step = &parents[parent];
+                    }
+                    return Some(tree);
+                }
+                for b in terms.iter().filter(|t| matches!(t, Term::Nonterminal(_))) {
+                    if !parents.contains_key(b) {
+                        if let Some(plan) = self.matches(&terms, &[b]) {
+                            parents.insert(b, Some((n, index, plan)));
+                        }
+                    }
+                }
+            }
+            i += 1;
+        }
+        None
+    }
+}
+
+struct CnfBuilder {
+    simplified: Alternatives,
+    cnf: Alternatives,
+    origins: IndexMap<Term, CnfOrigin>,
+    /// the CNF alternatives in the order they are added, with their source
+    sources: IndexMap<(Term, Vec<Term>), Option<usize>>,
+}
+
+impl CnfBuilder {
+    fn new(grammar: &Grammar) -> CnfBuilder {
+        CnfBuilder {
+            simplified: alternatives(grammar),
+            cnf: Alternatives::new(),
+            origins: IndexMap::new(),
+            sources: IndexMap::new(),
+        }
+    }
+
+    fn is_taken(&self, term: &Term) -> bool {
+        self.simplified.contains_key(term)
+            || self.origins.contains_key(term)
+            || self
+                .simplified
+                .values()
+                .flatten()
+                .flatten()
+                .any(|t| t == term)
+    }
+
+    /// `name` itself if it is not taken, a fresh nonterminal after it otherwise
+    fn fresh(&self, name: &str) -> Term {
+        let term = Term::Nonterminal(name.to_string());
+        match self.is_taken(&term) {
+            true => fresh_nonterminal(name, |term| self.is_taken(term)),
+            false => term,
+        }
+    }
+
+    fn push(&mut self, lhs: Term, rhs: Vec<Term>, source: Option<usize>) {
+        self.cnf.entry(lhs.clone()).or_default().push(rhs.clone());
+        self.sources.insert((lhs, rhs), source);
+    }
+
+    /// The nonterminal `T_a` for the terminal `a`, created on first use
+    fn lift(&mut self, terminal: &Term) -> Term {
+        if let Some((fresh, _)) = self
+            .origins
+            .iter()
+            .find(|(_, origin)| **origin == CnfOrigin::Terminal(terminal.clone()))
+        {
+            return fresh.clone();
+        }
+        let Term::Terminal(name) = terminal else {
+            unreachable!()
+        };
+        let fresh = self.fresh(&format!("T_{}", name));
+        debug!("[CNF] Lift {} into {}", terminal, fresh);
+        self.origins
+            .insert(fresh.clone(), CnfOrigin::Terminal(terminal.clone()));
+        self.push(fresh.clone(), vec![terminal.clone()], None);
+        fresh
+    }
+
+    /// Binarize `lhs -> terms`, the `production`-th alternative of the simplified grammar
+    fn binarize(&mut self, lhs: &Term, terms: &[Term], production: usize) {
+        if terms.len() <= 1 {
+            // A -> a, or S -> ε
+            self.push(lhs.clone(), terms.to_vec(), Some(production));
+            return;
+        }
+        let lifted = terms
+            .iter()
+            .map(|term| match term {
+                Term::Terminal(_) => self.lift(term),
+                Term::Nonterminal(_) => term.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let Term::Nonterminal(name) = lhs else {
+            unreachable!()
+        };
+        let mut head = lhs.clone();
+        for position in 1..lifted.len() - 1 {
+            let fresh = (1..)
+                .map(|i| Term::Nonterminal(format!("{}_{}", name, i)))
+                .find(|term| !self.is_taken(term))
+                .unwrap();
+            debug!("[CNF] Binarize r{} into {}", production, fresh);
+            self.origins.insert(
+                fresh.clone(),
+                CnfOrigin::Suffix {
+                    production,
+                    position,
+                },
+            );
+            self.push(
+                head,
+                vec![lifted[position - 1].clone(), fresh.clone()],
+                Some(production),
+            );
+            head = fresh;
+        }
+        let last = lifted[lifted.len() - 2..].to_vec();
+        self.push(head, last, Some(production));
+    }
+
+    fn build(mut self) -> Cnf {
+        let simplified = self.simplified.clone();
+        // the fresh nonterminals come after the original ones, the start symbol stays first
+        for lhs in simplified.keys() {
+            self.cnf.insert(lhs.clone(), vec![]);
+        }
+        let mut production = 0;
+        for (lhs, rhs) in simplified.iter() {
+            for terms in rhs.iter() {
+                self.binarize(lhs, terms, production);
+                production += 1;
+            }
+        }
+
+        let grammar = to_grammar(&self.cnf);
+        // the alternatives of `grammar` are grouped by lhs, in the order of `self.cnf`
+        let sources = self
+            .cnf
+            .iter()
+            .flat_map(|(lhs, rhs)| rhs.iter().map(move |terms| (lhs.clone(), terms.clone())))
+            .map(|key| self.sources[&key])
+            .collect();
+        let simplified = to_grammar(&simplified);
+        let productions = simplified
+            .productions_iter()
+            .flat_map(|p| p.rhs_iter().map(|rhs| (p.lhs.clone(), rhs.clone())))
+            .collect();
+        Cnf {
+            grammar,
+            simplified,
+            productions,
+            origins: self.origins,
+            sources,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::core::{tokens, Parser};
+    use crate::slr::builder::SLRTableBuilder;
+    use crate::transform::cnf::{is_cnf, to_cnf, CnfOrigin};
+    use crate::utils::augmentation::augment;
+    use bnf::{Grammar, Production, Term};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= <T> '*' <F> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn it_works() {
+        let grammar = grammar();
+        assert!(!is_cnf(&grammar));
+
+        let cnf = to_cnf(&grammar);
+        assert!(is_cnf(cnf.grammar()));
+        assert_eq!(
+            cnf.origin(&Term::from_str("<T_+>").unwrap()),
+            Some(&CnfOrigin::Terminal(Term::from_str("'+'").unwrap()))
+        );
+        assert_eq!(cnf.source(0), Some(0));
+        insta::assert_snapshot!(cnf);
+    }
+
+    #[test]
+    fn epsilon() {
+        let grammar: Grammar = r#"
+        <S> ::= 'a' <S> 'b' | 'ε'
+        "#
+        .parse()
+        .unwrap();
+        let expected: Grammar = r#"
+        <S'> ::= 'ε' | <T_a> <S'_1> | <T_a> <T_b>
+        <S> ::= <T_a> <S_1> | <T_a> <T_b>
+        <T_a> ::= 'a'
+        <T_b> ::= 'b'
+        <S'_1> ::= <S> <T_b>
+        <S_1> ::= <S> <T_b>
+        "#
+        .parse()
+        .unwrap();
+
+        let cnf = to_cnf(&grammar);
+        assert!(is_cnf(cnf.grammar()));
+        assert_eq!(cnf.grammar(), &expected);
+    }
+
+    #[test]
+    fn unbinarize() {
+        let grammar = grammar();
+        let cnf = to_cnf(&grammar);
+
+        let start = Term::from_str("<E>").unwrap();
+        let (augmented, augmentation) = augment(cnf.grammar(), &start).unwrap();
+        let slr = SLRTableBuilder::new(&augmented, &augmentation).build();
+        let tree = Parser::new(&slr)
+            .parse_tree(tokens("id * ( id + id )"))
+            .unwrap();
+
+        let simplified = cnf.unbinarize(&tree).unwrap();
+        assert_eq!(simplified.text(), tree.text());
+        assert!(simplified
+            .preorder()
+            .all(|(_, node)| cnf.origin(node.symbol()).is_none()));
+        insta::assert_snapshot!(simplified);
+
+        // a parse tree of another grammar
+        let other: Grammar = "<X'> ::= <X>\n<X> ::= 'x'".parse().unwrap();
+        let augmentation = Production::from_str("<X'> ::= <X>").unwrap();
+        let slr = SLRTableBuilder::new(&other, &augmentation).build();
+        let tree = Parser::new(&slr).parse_tree(tokens("x")).unwrap();
+        assert_eq!(cnf.unbinarize(&tree), None);
+        assert_eq!(cnf.restore(&grammar, &tree), None);
+    }
+}
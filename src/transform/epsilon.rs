@@ -0,0 +1,209 @@
+use crate::transform::{alternatives, to_grammar, Alternatives};
+use crate::utils::fresh_nonterminal;
+use bnf::{Grammar, Term};
+use indexmap::IndexSet;
+use log::debug;
+use std::fmt::{Display, Formatter};
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// The nonterminals which derive ε, in the order of their first definition
+pub fn nullable(grammar: &Grammar) -> IndexSet<Term> {
+    nullable_of(&alternatives(grammar))
+}
+
+fn nullable_of(alternatives: &Alternatives) -> IndexSet<Term> {
+    let mut nullable = IndexSet::new();
+    // iterate until nothing changes
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (lhs, rhs) in alternatives.iter() {
+            if !nullable.contains(lhs)
+                && rhs
+                    .iter()
+                    .any(|terms| terms.iter().all(|term| nullable.contains(term)))
+            {
+                nullable.insert(lhs.clone());
+                changed = true;
+            }
+        }
+    }
+    nullable.sort_by_key(|term| alternatives.get_index_of(term));
+    nullable
+}
+
+/// A grammar without ε-production, equivalent to the original one
+///
+/// If the language contains ε, a fresh start symbol `<S'> ::= <S> | 'ε'` is added,
+/// which is then the only ε-production, and does not appear on any right-hand side
+pub struct EpsilonElimination {
+    pub(crate) grammar: Grammar,
+    pub(crate) nullable: IndexSet<Term>,
+    /// the fresh start symbol, if the language contains ε
+    pub(crate) start: Option<Term>,
+}
+
+impl EpsilonElimination {
+    /// Remove every ε-production, the lhs of the first production is the start symbol
+    pub fn eliminate(grammar: &Grammar) -> EpsilonElimination {
+        let alternatives = alternatives(grammar);
+        let nullable = nullable_of(&alternatives);
+
+        let mut eliminated = Alternatives::new();
+        for (lhs, rhs) in alternatives.iter() {
+            let entry: &mut Vec<Vec<Term>> = eliminated.entry(lhs.clone()).or_default();
+            for terms in rhs.iter() {
+                for expanded in expand(terms, &nullable) {
+                    if !expanded.is_empty() && !entry.contains(&expanded) {
+                        entry.push(expanded);
+                    }
+                }
+            }
+        }
+        // a nonterminal which only derives ε has no alternative left,
+        // and every alternative using it is dropped as well
+        while let Some(i) = eliminated.values().position(|rhs| rhs.is_empty()) {
+            let (removed, _) = eliminated.shift_remove_index(i).unwrap();
+            debug!("[Epsilon] {} only derives ε, remove it", removed);
+            for rhs in eliminated.values_mut() {
+                rhs.retain(|terms| !terms.contains(&removed));
+            }
+        }
+
+        let start = alternatives
+            .keys()
+            .next()
+            .filter(|start| nullable.contains(*start))
+            .map(|start| {
+                let Term::Nonterminal(name) = start else {
+                    unreachable!()
+                };
+                let fresh = fresh_nonterminal(name, |term| {
+                    alternatives.contains_key(term)
+                        || alternatives.values().flatten().flatten().any(|t| t == term)
+                });
+                debug!("[Epsilon] {} is nullable, add {}", start, fresh);
+                let rhs = match eliminated.contains_key(start) {
+                    true => vec![vec![start.clone()], vec![]],
+                    // the language is {ε}
+                    false => vec![vec![]],
+                };
+                eliminated.shift_insert(0, fresh.clone(), rhs);
+                fresh
+            });
+
+        EpsilonElimination {
+            grammar: to_grammar(&eliminated),
+            nullable,
+            start,
+        }
+    }
+
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    pub fn into_grammar(self) -> Grammar {
+        self.grammar
+    }
+
+    pub fn nullable(&self) -> &IndexSet<Term> {
+        &self.nullable
+    }
+
+    /// The fresh start symbol, `None` if the language does not contain ε
+    pub fn start(&self) -> Option<&Term> {
+        self.start.as_ref()
+    }
+
+    pub fn nullable_table(&self) -> Table {
+        let mut builder = Builder::default();
+        builder.push_record(["Nullable"]); // header
+        for term in self.nullable.iter() {
+            builder.push_record([term.to_string()]);
+        }
+        builder.build()
+    }
+}
+
+impl Display for EpsilonElimination {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Grammar: \n{}", self.grammar))?;
+        f.write_fmt(format_args!("Nullable: \n{}", self.nullable_table()))
+    }
+}
+
+/// Every way to keep or drop each nullable occurrence of `terms`,
+/// keeping all the occurrences first
+fn expand(terms: &[Term], nullable: &IndexSet<Term>) -> Vec<Vec<Term>> {
+    terms.iter().fold(vec![vec![]], |prefixes, term| {
+        prefixes
+            .into_iter()
+            .flat_map(|prefix| {
+                let mut kept = prefix.clone();
+                kept.push(term.clone());
+                match nullable.contains(term) {
+                    true => vec![kept, prefix],
+                    false => vec![kept],
+                }
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transform::epsilon::{nullable, EpsilonElimination};
+    use bnf::{Grammar, Term};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <S> ::= <A> <B>
+        <A> ::= 'a' <A> | 'ε'
+        <B> ::= 'b' <B> | 'ε'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn it_works() {
+        let grammar = grammar();
+        let expected: Grammar = r#"
+        <S'> ::= <S> | 'ε'
+        <S> ::= <A> <B> | <A> | <B>
+        <A> ::= 'a' <A> | 'a'
+        <B> ::= 'b' <B> | 'b'
+        "#
+        .parse()
+        .unwrap();
+
+        let eliminated = EpsilonElimination::eliminate(&grammar);
+        assert_eq!(eliminated.grammar(), &expected);
+        assert_eq!(eliminated.start(), Some(&Term::from_str("<S'>").unwrap()));
+        assert_eq!(nullable(&grammar).len(), 3);
+        insta::assert_snapshot!(eliminated);
+    }
+
+    #[test]
+    fn without_epsilon_in_language() {
+        let grammar: Grammar = r#"
+        <S> ::= 'x' <A> <C> 'y'
+        <A> ::= <C> <C>
+        <C> ::= 'ε'
+        "#
+        .parse()
+        .unwrap();
+        let expected: Grammar = r#"
+        <S> ::= 'x' 'y'
+        "#
+        .parse()
+        .unwrap();
+
+        let eliminated = EpsilonElimination::eliminate(&grammar);
+        assert_eq!(eliminated.start(), None);
+        assert_eq!(eliminated.grammar(), &expected);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::transform::cnf::to_cnf;
+use crate::transform::epsilon::EpsilonElimination;
+use crate::transform::left_recursion::LeftRecursion;
+use crate::transform::unit::UnitElimination;
+use crate::transform::useless::UselessElimination;
+use crate::transform::{alternatives, to_grammar, Alternatives};
+use crate::utils::epsilon;
+use bnf::{Grammar, Term};
+use log::debug;
+use std::fmt::{Display, Formatter};
+
+/// A grammar in Greibach Normal Form, where every alternative is `A -> a B_1 ... B_n`,
+/// and only the start symbol may derive ε, by `S -> 'ε'`
+pub struct Gnf {
+    pub(crate) grammar: Grammar,
+    /// whether the language contains ε
+    pub(crate) epsilon: bool,
+}
+
+/// Convert `grammar` into Greibach Normal Form, the lhs of the first production is the start symbol
+///
+/// The grammar is first converted into CNF, without its ε alternative,
+/// its left recursion is eliminated, then the leading nonterminal of every alternative
+/// is substituted until every alternative begins with a terminal
+pub fn to_gnf(grammar: &Grammar) -> Gnf {
+    let cnf = to_cnf(grammar).into_grammar();
+    let mut simplified = alternatives(&cnf);
+    let Some((start, rhs)) = simplified.first_mut() else {
+        return Gnf {
+            grammar: cnf,
+            epsilon: false,
+        };
+    };
+    let start = start.clone();
+    let epsilon = rhs.iter().any(|terms| terms.is_empty());
+    rhs.retain(|terms| !terms.is_empty());
+    if rhs.is_empty() {
+        // the language is {ε}, which is already in GNF
+        return Gnf {
+            grammar: cnf,
+            epsilon,
+        };
+    }
+
+    // the ε of A' -> 𝛼 A' | ε is eliminated right after, no A' leads an alternative
+    let grammar = LeftRecursion::eliminate(&to_grammar(&simplified)).into_grammar();
+    let grammar = EpsilonElimination::eliminate(&grammar).into_grammar();
+    let grammar = UnitElimination::eliminate(&grammar).into_grammar();
+
+    let mut alternatives = substitute(alternatives(&grammar));
+    if epsilon {
+        debug!("[GNF] ε is in the language, add {} -> ε", start);
+        alternatives[&start].push(vec![]);
+    }
+    Gnf {
+        grammar: UselessElimination::eliminate(&to_grammar(&alternatives)).into_grammar(),
+        epsilon,
+    }
+}
+
+/// Replace every A -> B 𝛾 by A -> 𝛿_1 𝛾 | ... | 𝛿_k 𝛾, where B -> 𝛿_1 | ... | 𝛿_k,
+/// until every alternative begins with a terminal,
+/// which terminates because the grammar has no left recursion
+fn substitute(mut alternatives: Alternatives) -> Alternatives {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in (0..alternatives.len()).rev() {
+            let mut substituted = vec![];
+            for terms in alternatives[i].iter() {
+                match terms.split_first() {
+                    Some((head @ Term::Nonterminal(_), gamma)) => {
+                        debug!(
+                            "[GNF] Substitute {} in {}",
+                            head,
+                            alternatives.get_index(i).unwrap().0
+                        );
+                        for delta in alternatives[head].iter() {
+                            let terms = delta.iter().chain(gamma).cloned().collect();
+                            if !substituted.contains(&terms) {
+                                substituted.push(terms);
+                            }
+                        }
+                        changed = true;
+                    }
+                    _ if !substituted.contains(terms) => substituted.push(terms.clone()),
+                    _ => {}
+                }
+            }
+            alternatives[i] = substituted;
+        }
+    }
+    alternatives
+}
+
+/// Whether every alternative of `grammar` is `A -> a B_1 ... B_n`,
+/// or `S -> 'ε'` where `S` is the start symbol and is used on no right-hand side
+pub fn is_gnf(grammar: &Grammar) -> bool {
+    let start = grammar.productions_iter().next().map(|p| &p.lhs);
+    let start_on_rhs = grammar
+        .productions_iter()
+        .flat_map(|p| p.rhs_iter())
+        .flat_map(|expr| expr.terms_iter())
+        .any(|term| Some(term) == start);
+    grammar.productions_iter().all(|production| {
+        production.rhs_iter().all(|expr| {
+            let terms = expr.terms_iter().collect::<Vec<_>>();
+            match terms.split_first() {
+                Some((head, [])) if *head == epsilon() => {
+                    Some(&production.lhs) == start && !start_on_rhs
+                }
+                Some((Term::Terminal(_), tail)) => {
+                    tail.iter().all(|term| matches!(term, Term::Nonterminal(_)))
+                }
+                _ => false,
+            }
+        })
+    })
+}
+
+impl Gnf {
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    pub fn into_grammar(self) -> Grammar {
+        self.grammar
+    }
+
+    /// Whether the language contains ε, derived by the start symbol only
+    pub fn has_epsilon(&self) -> bool {
+        self.epsilon
+    }
+}
+
+impl Display for Gnf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Grammar: \n{}", self.grammar))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transform::gnf::{is_gnf, to_gnf};
+    use crate::utils::language::sentences;
+    use bnf::Grammar;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= <T> '*' <F> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn it_works() {
+        let grammar = grammar();
+        assert!(!is_gnf(&grammar));
+
+        let gnf = to_gnf(&grammar);
+        assert!(is_gnf(gnf.grammar()));
+        assert!(!gnf.has_epsilon());
+        assert_eq!(sentences(&grammar, 7), sentences(gnf.grammar(), 7));
+        insta::assert_snapshot!(gnf);
+    }
+
+    #[test]
+    fn epsilon() {
+        let grammar: Grammar = r#"
+        <S> ::= <A> <S> 'b' | 'ε'
+        <A> ::= <A> 'a' | 'a' | 'ε'
+        "#
+        .parse()
+        .unwrap();
+
+        let gnf = to_gnf(&grammar);
+        assert!(is_gnf(gnf.grammar()));
+        assert!(gnf.has_epsilon());
+        assert_eq!(sentences(&grammar, 6), sentences(gnf.grammar(), 6));
+    }
+
+    #[test]
+    fn only_epsilon() {
+        let grammar: Grammar = r#"
+        <S> ::= <A> <A>
+        <A> ::= 'ε'
+        "#
+        .parse()
+        .unwrap();
+        let expected: Grammar = r#"
+        <S'> ::= 'ε'
+        "#
+        .parse()
+        .unwrap();
+
+        let gnf = to_gnf(&grammar);
+        assert_eq!(gnf.grammar(), &expected);
+        assert!(gnf.has_epsilon());
+    }
+}
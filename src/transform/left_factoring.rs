@@ -0,0 +1,299 @@
+use crate::transform::{alternatives, to_grammar, Alternatives};
+use crate::utils::fresh_nonterminal;
+use bnf::{Grammar, Term};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use log::debug;
+use std::fmt::{Display, Formatter};
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// A single step of left factoring,
+/// `lhs -> prefix 𝛽_1 | ... | prefix 𝛽_n` is rewritten into
+/// `lhs -> prefix fresh` and `fresh -> 𝛽_1 | ... | 𝛽_n`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Factoring {
+    pub(crate) lhs: Term,
+    pub(crate) prefix: Vec<Term>,
+    pub(crate) fresh: Term,
+    /// the 𝛽s, empty for ε
+    pub(crate) suffixes: Vec<Vec<Term>>,
+}
+
+impl Factoring {
+    pub fn lhs(&self) -> &Term {
+        &self.lhs
+    }
+
+    pub fn prefix(&self) -> &[Term] {
+        &self.prefix
+    }
+
+    pub fn fresh(&self) -> &Term {
+        &self.fresh
+    }
+
+    pub fn suffixes(&self) -> &[Vec<Term>] {
+        &self.suffixes
+    }
+}
+
+/// A grammar in which no two alternatives of a nonterminal share a common prefix,
+/// equivalent to the original one
+pub struct LeftFactoring {
+    pub(crate) grammar: Grammar,
+    /// the fresh nonterminals, mapped to the original nonterminal they are factored from
+    pub(crate) origins: IndexMap<Term, Term>,
+    /// every rewrite, in the order it is applied
+    pub(crate) factorings: Vec<Factoring>,
+}
+
+impl LeftFactoring {
+    /// Factor out the longest common prefix of alternatives until there is none
+    pub fn factor(grammar: &Grammar) -> LeftFactoring {
+        LeftFactoringBuilder::new(grammar).build()
+    }
+
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    pub fn into_grammar(self) -> Grammar {
+        self.grammar
+    }
+
+    pub fn origins(&self) -> &IndexMap<Term, Term> {
+        &self.origins
+    }
+
+    /// The original nonterminal of `term`, `term` itself if it is not fresh
+    pub fn origin<'a>(&'a self, term: &'a Term) -> &'a Term {
+        self.origins.get(term).unwrap_or(term)
+    }
+
+    pub fn factorings(&self) -> &[Factoring] {
+        &self.factorings
+    }
+
+    /// Whether the grammar is rewritten at all
+    pub fn is_factored(&self) -> bool {
+        !self.factorings.is_empty()
+    }
+
+    pub fn factoring_table(&self) -> Table {
+        let mut builder = Builder::default();
+        builder.push_record(["Nonterminal", "Prefix", "Fresh", "Suffixes"]); // header
+        for factoring in self.factorings.iter() {
+            builder.push_record([
+                factoring.lhs.to_string(),
+                factoring.prefix.iter().join(" "),
+                factoring.fresh.to_string(),
+                factoring
+                    .suffixes
+                    .iter()
+                    .map(|suffix| match suffix.is_empty() {
+                        true => "'ε'".to_string(),
+                        false => suffix.iter().join(" "),
+                    })
+                    .join(" | "),
+            ]);
+        }
+        builder.build()
+    }
+}
+
+impl Display for LeftFactoring {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Grammar: \n{}", self.grammar))?;
+        f.write_fmt(format_args!("Factorings: \n{}", self.factoring_table()))
+    }
+}
+
+struct LeftFactoringBuilder {
+    alternatives: Alternatives,
+    origins: IndexMap<Term, Term>,
+    factorings: Vec<Factoring>,
+}
+
+impl LeftFactoringBuilder {
+    fn new(grammar: &Grammar) -> LeftFactoringBuilder {
+        LeftFactoringBuilder {
+            alternatives: alternatives(grammar),
+            origins: IndexMap::new(),
+            factorings: vec![],
+        }
+    }
+
+    fn is_taken(&self, term: &Term) -> bool {
+        self.alternatives.contains_key(term)
+            || self
+                .alternatives
+                .values()
+                .flatten()
+                .flatten()
+                .any(|t| t == term)
+    }
+
+    /// The longest prefix common to two or more of the `alternatives`, empty if none
+    fn longest_common_prefix(alternatives: &[Vec<Term>]) -> &[Term] {
+        alternatives
+            .iter()
+            .tuple_combinations()
+            .map(|(a, b)| {
+                let len = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+                &a[..len]
+            })
+            .fold(&[], |longest, prefix| {
+                if prefix.len() > longest.len() {
+                    prefix
+                } else {
+                    longest
+                }
+            })
+    }
+
+    /// Factor the longest common prefix out of the alternatives of the `i`-th nonterminal,
+    /// return false if there is nothing to factor
+    fn factor(&mut self, i: usize) -> bool {
+        let (lhs, alternatives) = self.alternatives.get_index(i).unwrap();
+        let prefix = Self::longest_common_prefix(alternatives).to_vec();
+        if prefix.is_empty() {
+            return false;
+        }
+
+        let origin = self.origins.get(lhs).unwrap_or(lhs).clone();
+        let Term::Nonterminal(name) = &origin else {
+            unreachable!()
+        };
+        let fresh = fresh_nonterminal(name, |term| self.is_taken(term));
+        debug!(
+            "[Left Factoring] Factor {} out of {} into {}",
+            prefix.iter().join(" "),
+            lhs,
+            fresh
+        );
+
+        let (factored, rest): (Vec<_>, Vec<_>) = alternatives
+            .iter()
+            .cloned()
+            .partition(|alternative| alternative.starts_with(&prefix));
+        let suffixes = factored
+            .into_iter()
+            .map(|alternative| alternative[prefix.len()..].to_vec())
+            .collect::<Vec<_>>();
+
+        let lhs = lhs.clone();
+        let mut alternatives = rest;
+        let position = self.alternatives[i]
+            .iter()
+            .position(|alternative| alternative.starts_with(&prefix))
+            .unwrap();
+        alternatives.insert(
+            position,
+            prefix
+                .iter()
+                .cloned()
+                .chain(std::iter::once(fresh.clone()))
+                .collect(),
+        );
+        self.alternatives[i] = alternatives;
+        self.alternatives
+            .shift_insert(i + 1, fresh.clone(), suffixes.clone());
+        self.origins.insert(fresh.clone(), origin);
+        self.factorings.push(Factoring {
+            lhs,
+            prefix,
+            fresh,
+            suffixes,
+        });
+        true
+    }
+
+    fn build(mut self) -> LeftFactoring {
+        let mut i = 0;
+        while i < self.alternatives.len() {
+            // factor the same nonterminal again, until no two alternatives share a prefix
+            if !self.factor(i) {
+                i += 1;
+            }
+        }
+        LeftFactoring {
+            grammar: to_grammar(&self.alternatives),
+            origins: self.origins,
+            factorings: self.factorings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ll1::core::LL1Table;
+    use crate::transform::left_factoring::LeftFactoring;
+    use bnf::{Grammar, Term};
+    use std::str::FromStr;
+
+    #[test]
+    fn it_works() {
+        let grammar: Grammar = r#"
+        <S> ::= 'i' <E> 't' <S> | 'i' <E> 't' <S> 'e' <S> | 'a'
+        <E> ::= 'b'
+        "#
+        .parse()
+        .unwrap();
+        let expected: Grammar = r#"
+        <S> ::= 'i' <E> 't' <S> <S'> | 'a'
+        <S'> ::= 'ε' | 'e' <S>
+        <E> ::= 'b'
+        "#
+        .parse()
+        .unwrap();
+
+        let factored = LeftFactoring::factor(&grammar);
+        assert_eq!(factored.grammar(), &expected);
+        assert_eq!(factored.factorings().len(), 1);
+        insta::assert_snapshot!(factored);
+    }
+
+    #[test]
+    fn repeated() {
+        let grammar: Grammar = r#"
+        <A> ::= 'a' 'b' 'c' | 'a' 'b' 'd' | 'a' 'e' | 'f'
+        "#
+        .parse()
+        .unwrap();
+        let expected: Grammar = r#"
+        <A> ::= 'a' <A''> | 'f'
+        <A''> ::= 'b' <A'> | 'e'
+        <A'> ::= 'c' | 'd'
+        "#
+        .parse()
+        .unwrap();
+
+        let factored = LeftFactoring::factor(&grammar);
+        assert_eq!(factored.grammar(), &expected);
+        assert_eq!(
+            factored.origin(&Term::from_str("<A''>").unwrap()),
+            &Term::from_str("<A>").unwrap()
+        );
+        insta::assert_snapshot!(factored.factoring_table());
+    }
+
+    #[test]
+    fn ll1() {
+        let grammar: Grammar = r#"
+        <E> ::= <T> '+' <E> | <T>
+        <T> ::= <F> '*' <T> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+        let start = Term::from_str("<E>").unwrap();
+
+        let ll1 = LL1Table::new(&grammar, &start);
+        assert!(ll1.has_conflicts());
+
+        let grammar = LeftFactoring::factor(&grammar).into_grammar();
+        let ll1 = LL1Table::new(&grammar, &start);
+        assert!(!ll1.has_conflicts());
+    }
+}
@@ -0,0 +1,320 @@
+use crate::transform::{alternatives, to_grammar, Alternatives};
+use crate::utils::fresh_nonterminal;
+use bnf::{Grammar, Term};
+use indexmap::IndexMap;
+use log::debug;
+use std::fmt::{Display, Formatter};
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// A grammar without left recursion, equivalent to the original one
+///
+/// The elimination assumes the original grammar has no cycle `A =>+ A`,
+/// and no left recursion hidden behind an ε-production
+pub struct LeftRecursion {
+    pub(crate) grammar: Grammar,
+    /// the fresh nonterminals, mapped to the nonterminal they are split from
+    pub(crate) origins: IndexMap<Term, Term>,
+    /// the nonterminals whose alternatives are all left recursive, and those left without
+    /// alternative once they are dropped, they derive no terminal string
+    pub(crate) unproductive: Vec<Term>,
+}
+
+impl LeftRecursion {
+    /// Remove both the immediate and the indirect left recursion of `grammar`
+    pub fn eliminate(grammar: &Grammar) -> LeftRecursion {
+        LeftRecursionBuilder::new(grammar).build()
+    }
+
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    pub fn into_grammar(self) -> Grammar {
+        self.grammar
+    }
+
+    pub fn origins(&self) -> &IndexMap<Term, Term> {
+        &self.origins
+    }
+
+    pub fn unproductive(&self) -> &[Term] {
+        &self.unproductive
+    }
+
+    /// The original nonterminal of `term`, `term` itself if it is not fresh
+    pub fn origin<'a>(&'a self, term: &'a Term) -> &'a Term {
+        self.origins.get(term).unwrap_or(term)
+    }
+
+    pub fn origin_table(&self) -> Table {
+        let mut builder = Builder::default();
+        builder.push_record(["Nonterminal", "Origin"]); // header
+        for (fresh, origin) in self.origins.iter() {
+            builder.push_record([fresh.to_string(), origin.to_string()]);
+        }
+        builder.build()
+    }
+}
+
+impl Display for LeftRecursion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Grammar: \n{}", self.grammar))?;
+        f.write_fmt(format_args!("Origins: \n{}", self.origin_table()))
+    }
+}
+
+struct LeftRecursionBuilder {
+    /// the alternatives of A_1, ..., A_n in order
+    alternatives: Alternatives,
+    /// the fresh A_i', in front of which A_i is defined
+    fresh: IndexMap<Term, (Term, Vec<Vec<Term>>)>,
+}
+
+impl LeftRecursionBuilder {
+    fn new(grammar: &Grammar) -> LeftRecursionBuilder {
+        LeftRecursionBuilder {
+            alternatives: alternatives(grammar),
+            fresh: IndexMap::new(),
+        }
+    }
+
+    fn is_taken(&self, term: &Term) -> bool {
+        self.alternatives.contains_key(term)
+            || self.fresh.values().any(|(fresh, _)| fresh == term)
+            || self
+                .alternatives
+                .values()
+                .flatten()
+                .flatten()
+                .any(|t| t == term)
+    }
+
+    /// Replace every A_i -> A_j 𝛾 by A_i -> 𝛿_1 𝛾 | ... | 𝛿_k 𝛾,
+    /// where A_j -> 𝛿_1 | ... | 𝛿_k are the current alternatives of A_j
+    fn substitute(&mut self, i: usize, j: usize) {
+        let (a_j, deltas) = self.alternatives.get_index(j).unwrap();
+        let (a_j, deltas) = (a_j.clone(), deltas.clone());
+        let (a_i, alternatives) = self.alternatives.get_index_mut(i).unwrap();
+
+        let mut substituted = vec![];
+        for alternative in alternatives.drain(..) {
+            match alternative.split_first() {
+                Some((head, gamma)) if *head == a_j => {
+                    debug!("[Left Recursion] Substitute {} in {}", a_j, a_i);
+                    for delta in deltas.iter() {
+                        let terms = delta.iter().chain(gamma).cloned().collect();
+                        if !substituted.contains(&terms) {
+                            substituted.push(terms);
+                        }
+                    }
+                }
+                _ if !substituted.contains(&alternative) => substituted.push(alternative),
+                _ => {}
+            }
+        }
+        *alternatives = substituted;
+    }
+
+    /// Replace A -> A 𝛼_1 | ... | A 𝛼_m | 𝛽_1 | ... | 𝛽_n by
+    /// A -> 𝛽_1 A' | ... | 𝛽_n A' and A' -> 𝛼_1 A' | ... | 𝛼_m A' | ε
+    fn eliminate_immediate(&mut self, i: usize) {
+        let (a, alternatives) = self.alternatives.get_index(i).unwrap();
+        let (alphas, betas): (Vec<_>, Vec<_>) = alternatives
+            .iter()
+            .partition(|alternative| alternative.first() == Some(a));
+        if alphas.is_empty() {
+            return;
+        }
+        if betas.is_empty() {
+            // A -> A 𝛼_1 | ... | A 𝛼_m derives no terminal string
+            debug!(
+                "[Left Recursion] Drop {}, every alternative is left recursive",
+                a
+            );
+            self.alternatives[i] = vec![];
+            return;
+        }
+
+        let Term::Nonterminal(name) = a else {
+            unreachable!()
+        };
+        let fresh = fresh_nonterminal(name, |term| self.is_taken(term));
+        debug!("[Left Recursion] Split {} into {} and {}", a, a, fresh);
+
+        let primed = alphas
+            .iter()
+            // A -> A is a cycle, which adds nothing to the language
+            .filter(|alpha| alpha.len() > 1)
+            .map(|alpha| {
+                alpha[1..]
+                    .iter()
+                    .cloned()
+                    .chain(std::iter::once(fresh.clone()))
+                    .collect()
+            })
+            .chain(std::iter::once(vec![]))
+            .collect();
+        let betas = betas
+            .iter()
+            .map(|beta| {
+                beta.iter()
+                    .cloned()
+                    .chain(std::iter::once(fresh.clone()))
+                    .collect()
+            })
+            .collect();
+
+        let a = a.clone();
+        self.alternatives[i] = betas;
+        self.fresh.insert(a.clone(), (fresh, primed));
+    }
+
+    fn build(mut self) -> LeftRecursion {
+        for i in 0..self.alternatives.len() {
+            for j in 0..i {
+                self.substitute(i, j);
+            }
+            self.eliminate_immediate(i);
+        }
+
+        let mut alternatives = Alternatives::new();
+        let mut origins = IndexMap::new();
+        for (a, rhs) in self.alternatives {
+            alternatives.insert(a.clone(), rhs);
+            if let Some((fresh, primed)) = self.fresh.swap_remove(&a) {
+                alternatives.insert(fresh.clone(), primed);
+                origins.insert(fresh, a);
+            }
+        }
+
+        // drop the nonterminals without alternative, and every alternative using them
+        let mut unproductive = vec![];
+        while let Some(index) = alternatives.values().position(|rhs| rhs.is_empty()) {
+            let (a, _) = alternatives.shift_remove_index(index).unwrap();
+            for rhs in alternatives.values_mut() {
+                rhs.retain(|terms| !terms.contains(&a));
+            }
+            unproductive.push(a);
+        }
+        LeftRecursion {
+            grammar: to_grammar(&alternatives),
+            origins,
+            unproductive,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ll1::core::LL1Table;
+    use crate::transform::left_recursion::LeftRecursion;
+    use bnf::{Grammar, Term};
+    use std::str::FromStr;
+
+    #[test]
+    fn immediate() {
+        let grammar: Grammar = r#"
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= <T> '*' <F> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+        let expected: Grammar = r#"
+        <E> ::= <T> <E'>
+        <E'> ::= '+' <T> <E'> | 'ε'
+        <T> ::= <F> <T'>
+        <T'> ::= '*' <F> <T'> | 'ε'
+        <F> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+
+        let eliminated = LeftRecursion::eliminate(&grammar);
+        assert_eq!(eliminated.grammar(), &expected);
+        assert_eq!(
+            eliminated.origin(&Term::from_str("<T'>").unwrap()),
+            &Term::from_str("<T>").unwrap()
+        );
+        insta::assert_snapshot!(eliminated);
+
+        let start = Term::from_str("<E>").unwrap();
+        let grammar = eliminated.into_grammar();
+        let ll1 = LL1Table::new(&grammar, &start);
+        assert!(!ll1.has_conflicts());
+    }
+
+    #[test]
+    fn indirect() {
+        let grammar: Grammar = r#"
+        <S> ::= <A> 'a' | 'b'
+        <A> ::= <A> 'c' | <S> 'd' | 'ε'
+        "#
+        .parse()
+        .unwrap();
+        let expected: Grammar = r#"
+        <S> ::= <A> 'a' | 'b'
+        <A> ::= 'b' 'd' <A'> | <A'>
+        <A'> ::= 'c' <A'> | 'a' 'd' <A'> | 'ε'
+        "#
+        .parse()
+        .unwrap();
+
+        let eliminated = LeftRecursion::eliminate(&grammar);
+        assert_eq!(eliminated.grammar(), &expected);
+    }
+
+    #[test]
+    fn fresh_name() {
+        let grammar: Grammar = r#"
+        <A> ::= <A> 'x' | <A'>
+        <A'> ::= 'y'
+        "#
+        .parse()
+        .unwrap();
+        let expected: Grammar = r#"
+        <A> ::= <A'> <A''>
+        <A''> ::= 'x' <A''> | 'ε'
+        <A'> ::= 'y'
+        "#
+        .parse()
+        .unwrap();
+
+        let eliminated = LeftRecursion::eliminate(&grammar);
+        assert_eq!(eliminated.grammar(), &expected);
+        assert_eq!(eliminated.origins().len(), 1);
+    }
+
+    #[test]
+    fn only_left_recursive() {
+        let grammar: Grammar = r#"
+        <S> ::= <A> | <B> 'c' | 'b'
+        <A> ::= <A> 'a'
+        <B> ::= <A> 'b'
+        "#
+        .parse()
+        .unwrap();
+        let expected: Grammar = r#"
+        <S> ::= 'b'
+        "#
+        .parse()
+        .unwrap();
+
+        let eliminated = LeftRecursion::eliminate(&grammar);
+        assert_eq!(eliminated.grammar(), &expected);
+        assert!(eliminated.origins().is_empty());
+        assert_eq!(
+            eliminated.unproductive(),
+            &[
+                Term::from_str("<A>").unwrap(),
+                Term::from_str("<B>").unwrap()
+            ]
+        );
+
+        let grammar: Grammar = "<A> ::= <A> 'a'".parse().unwrap();
+        let eliminated = LeftRecursion::eliminate(&grammar);
+        assert_eq!(eliminated.grammar().productions_iter().count(), 0);
+        assert_eq!(eliminated.unproductive(), &[Term::from_str("<A>").unwrap()]);
+    }
+}
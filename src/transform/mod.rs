@@ -0,0 +1,54 @@
+use crate::utils::epsilon;
+use bnf::{Expression, Grammar, Production, Term};
+use indexmap::IndexMap;
+
+pub mod cnf;
+pub mod epsilon;
+pub mod gnf;
+pub mod left_factoring;
+pub mod left_recursion;
+pub mod normalize;
+pub mod unit;
+pub mod useless;
+
+/// The alternatives of every nonterminal, in the order of their first definition,
+/// productions sharing a lhs are merged, `'ε'` is the empty alternative
+pub(crate) type Alternatives = IndexMap<Term, Vec<Vec<Term>>>;
+
+pub(crate) fn alternatives(grammar: &Grammar) -> Alternatives {
+    let mut alternatives = Alternatives::new();
+    for production in grammar.productions_iter() {
+        let entry: &mut Vec<Vec<Term>> = alternatives.entry(production.lhs.clone()).or_default();
+        for expr in production.rhs_iter() {
+            let terms = expr
+                .terms_iter()
+                .filter(|term| *term != epsilon())
+                .cloned()
+                .collect::<Vec<_>>();
+            if !entry.contains(&terms) {
+                entry.push(terms);
+            }
+        }
+    }
+    alternatives
+}
+
+/// Build the grammar back, the empty alternative is written as `'ε'`
+pub(crate) fn to_grammar(alternatives: &Alternatives) -> Grammar {
+    Grammar::from_parts(
+        alternatives
+            .iter()
+            .map(|(lhs, rhs)| {
+                Production::from_parts(
+                    lhs.clone(),
+                    rhs.iter()
+                        .map(|terms| match terms.is_empty() {
+                            true => Expression::from_parts(vec![epsilon().clone()]),
+                            false => Expression::from_parts(terms.clone()),
+                        })
+                        .collect(),
+                )
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,117 @@
+use crate::transform::{alternatives, to_grammar};
+use bnf::Grammar;
+
+/// Canonicalize `grammar`: every nonterminal is defined by a single production,
+/// in the order of its first definition, without duplicated alternatives,
+/// and ε is only written as a whole alternative `'ε'`
+pub fn normalize(grammar: &Grammar) -> Grammar {
+    to_grammar(&alternatives(grammar))
+}
+
+/// Whether `grammar` is already in the form produced by [`normalize`]
+pub fn is_normalized(grammar: &Grammar) -> bool {
+    normalize(grammar) == *grammar
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ir::grammar::InternedGrammar;
+    use crate::ll1::core::LL1Table;
+    use crate::slr::builder::SLRTableBuilder;
+    use crate::transform::normalize::{is_normalized, normalize};
+    use crate::utils::first::First;
+    use crate::utils::follow::Follow;
+    use bnf::{Grammar, Production, Term};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <S> ::= <A> <C> 'v'
+        <A> ::= 'x'
+        <C> ::= <A> 'x'
+        <A> ::= 'y' | 'x'
+        <C> ::= 'ε'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn it_works() {
+        let grammar = grammar();
+        let expected: Grammar = r#"
+        <S> ::= <A> <C> 'v'
+        <A> ::= 'x' | 'y'
+        <C> ::= <A> 'x' | 'ε'
+        "#
+        .parse()
+        .unwrap();
+
+        assert!(!is_normalized(&grammar));
+        let normalized = normalize(&grammar);
+        assert_eq!(normalized, expected);
+        assert!(is_normalized(&normalized));
+    }
+
+    #[test]
+    fn same_first_and_follow() {
+        let grammar = grammar();
+        let normalized = InternedGrammar::new(&normalize(&grammar));
+        let grammar = InternedGrammar::new(&grammar);
+
+        let first = First::new(&grammar);
+        let normalized_first = First::new(&normalized);
+        assert_eq!(
+            first.tabled(&grammar).to_string(),
+            normalized_first.tabled(&normalized).to_string()
+        );
+        insta::assert_snapshot!(first.tabled(&grammar));
+
+        let follow = Follow::new(&grammar, &first, grammar.start().unwrap());
+        assert_eq!(
+            follow.tabled(&grammar).to_string(),
+            Follow::new(&normalized, &normalized_first, normalized.start().unwrap())
+                .tabled(&normalized)
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn same_tables() {
+        let grammar = grammar();
+        let normalized = normalize(&grammar);
+        let start = Term::from_str("<S>").unwrap();
+
+        // the duplicated <A> -> 'x' are two productions competing for M[<A>, 'x'],
+        // which are merged into one
+        let ll1 = LL1Table::new(&grammar, &start);
+        assert_eq!(ll1.conflicts().len(), 1);
+        assert_eq!(
+            ll1.conflicts()[0].lookahead(),
+            &Term::from_str("'x'").unwrap()
+        );
+        assert!(!LL1Table::new(&normalized, &start).has_conflicts());
+
+        // the alternatives of <C> are numbered the same before and after
+        let grammar: Grammar = r#"
+        <S'> ::= <S>
+        <S> ::= <O> 'v' <C>
+        <O> ::= 'n'
+        <C> ::= <S>
+        <C> ::= 'n'
+        "#
+        .parse()
+        .unwrap();
+        let normalized = normalize(&grammar);
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+        assert!(!slr.has_conflicts());
+        assert_eq!(
+            slr.to_string(),
+            SLRTableBuilder::new(&normalized, &augmentation)
+                .build()
+                .to_string()
+        );
+    }
+}
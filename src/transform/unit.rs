@@ -0,0 +1,168 @@
+use crate::transform::{alternatives, to_grammar, Alternatives};
+use bnf::{Grammar, Term};
+use itertools::Itertools;
+use log::debug;
+use std::fmt::{Display, Formatter};
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// A grammar without unit production `A -> B`, equivalent to the original one
+///
+/// Nonterminals only used through unit productions may become unreachable,
+/// see [`UselessElimination`](crate::transform::useless::UselessElimination)
+pub struct UnitElimination {
+    pub(crate) grammar: Grammar,
+    /// every unit pair (A, B) such that A =>* B by unit productions only, A != B
+    pub(crate) pairs: Vec<(Term, Term)>,
+}
+
+impl UnitElimination {
+    /// Replace the unit productions of every A by the non-unit alternatives
+    /// of every B such that (A, B) is a unit pair
+    pub fn eliminate(grammar: &Grammar) -> UnitElimination {
+        let alternatives = alternatives(grammar);
+        let pairs = unit_pairs(&alternatives);
+
+        let mut eliminated = Alternatives::new();
+        for a in alternatives.keys() {
+            let entry: &mut Vec<Vec<Term>> = eliminated.entry(a.clone()).or_default();
+            let reachable = std::iter::once(a)
+                .chain(pairs.iter().filter(|(from, _)| from == a).map(|(_, to)| to));
+            for b in reachable {
+                for terms in alternatives[b].iter().filter(|terms| !is_unit(terms)) {
+                    if !entry.contains(terms) {
+                        entry.push(terms.clone());
+                    }
+                }
+            }
+        }
+
+        UnitElimination {
+            grammar: to_grammar(&eliminated),
+            pairs,
+        }
+    }
+
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    pub fn into_grammar(self) -> Grammar {
+        self.grammar
+    }
+
+    pub fn pairs(&self) -> &[(Term, Term)] {
+        &self.pairs
+    }
+
+    pub fn pair_table(&self) -> Table {
+        let mut builder = Builder::default();
+        builder.push_record(["From", "To"]); // header
+        for (from, to) in self.pairs.iter() {
+            builder.push_record([from.to_string(), to.to_string()]);
+        }
+        builder.build()
+    }
+}
+
+impl Display for UnitElimination {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Grammar: \n{}", self.grammar))?;
+        f.write_fmt(format_args!("Unit Pairs: \n{}", self.pair_table()))
+    }
+}
+
+fn is_unit(terms: &[Term]) -> bool {
+    matches!(terms, [Term::Nonterminal(_)])
+}
+
+/// The closure of the unit productions, (A, B) for every A =>+ B, A != B
+fn unit_pairs(alternatives: &Alternatives) -> Vec<(Term, Term)> {
+    let mut pairs = vec![];
+    for a in alternatives.keys() {
+        let mut stack = vec![a];
+        let mut reached = vec![a];
+        while let Some(from) = stack.pop() {
+            let units = alternatives
+                .get(from)
+                .into_iter()
+                .flatten()
+                .filter(|terms| is_unit(terms))
+                .map(|terms| &terms[0]);
+            for to in units {
+                if !reached.contains(&to) {
+                    debug!("[Unit] ({}, {}) is a unit pair", a, to);
+                    reached.push(to);
+                    stack.push(to);
+                }
+            }
+        }
+        pairs.extend(
+            reached
+                .into_iter()
+                .skip(1)
+                // an undefined nonterminal has no alternative to bring
+                .filter(|to| alternatives.contains_key(*to))
+                .sorted_by_key(|to| alternatives.get_index_of(*to))
+                .map(|to| (a.clone(), to.clone())),
+        );
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transform::unit::UnitElimination;
+    use bnf::{Grammar, Term};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= <T> '*' <F> | <F>
+        <F> ::= '(' <E> ')' | 'id'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn it_works() {
+        let grammar = grammar();
+        let expected: Grammar = r#"
+        <E> ::= <E> '+' <T> | <T> '*' <F> | '(' <E> ')' | 'id'
+        <T> ::= <T> '*' <F> | '(' <E> ')' | 'id'
+        <F> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+
+        let eliminated = UnitElimination::eliminate(&grammar);
+        assert_eq!(eliminated.grammar(), &expected);
+        assert_eq!(eliminated.pairs().len(), 3);
+        insta::assert_snapshot!(eliminated);
+    }
+
+    #[test]
+    fn cycle() {
+        let grammar: Grammar = r#"
+        <A> ::= <B> | 'a'
+        <B> ::= <A> | <A> 'b'
+        "#
+        .parse()
+        .unwrap();
+        let expected: Grammar = r#"
+        <A> ::= 'a' | <A> 'b'
+        <B> ::= <A> 'b' | 'a'
+        "#
+        .parse()
+        .unwrap();
+
+        let eliminated = UnitElimination::eliminate(&grammar);
+        assert_eq!(eliminated.grammar(), &expected);
+        assert!(eliminated.pairs().contains(&(
+            Term::from_str("<B>").unwrap(),
+            Term::from_str("<A>").unwrap()
+        )));
+    }
+}
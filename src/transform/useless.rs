@@ -0,0 +1,214 @@
+use crate::utils::symbols;
+use crate::utils::validate::{productive, reachable};
+use bnf::{Grammar, Production, Term};
+use log::debug;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use tabled::builder::Builder;
+use tabled::Table;
+
+/// A grammar in which every symbol is reachable from the start symbol
+/// and derives a string of terminals, equivalent to the original one
+pub struct UselessElimination {
+    pub(crate) grammar: Grammar,
+    /// the nonterminals which derive no terminal string
+    pub(crate) unproductive: Vec<Term>,
+    /// the symbols which are unreachable once the unproductive ones are removed
+    pub(crate) unreachable: Vec<Term>,
+    /// every removed alternative, as a production of its own
+    pub(crate) removed: Vec<Production>,
+}
+
+impl UselessElimination {
+    /// Remove the unproductive symbols, then the unreachable ones,
+    /// the lhs of the first production is the start symbol
+    pub fn eliminate(grammar: &Grammar) -> UselessElimination {
+        let Some(start) = grammar.productions_iter().next().map(|p| p.lhs.clone()) else {
+            return UselessElimination {
+                grammar: Grammar::from_parts(vec![]),
+                unproductive: vec![],
+                unreachable: vec![],
+                removed: vec![],
+            };
+        };
+        let mut removed = vec![];
+
+        // the order matters, removing unproductive symbols may make others unreachable
+        let productive = productive(grammar);
+        let unproductive = symbols(grammar)
+            .filter(|term| matches!(term, Term::Nonterminal(_)) && !productive.contains(term))
+            .cloned()
+            .collect::<Vec<_>>();
+        let productive_grammar = retain(grammar, &mut removed, |lhs, terms| {
+            productive.contains(lhs) && terms.iter().all(|term| !unproductive.contains(term))
+        });
+
+        let reachable = reachable(&productive_grammar, &start);
+        let reduced = retain(&productive_grammar, &mut removed, |lhs, _| {
+            reachable.contains(lhs)
+        });
+        let remaining = symbols(&reduced).collect::<HashSet<_>>();
+        let unreachable = symbols(grammar)
+            .filter(|term| !remaining.contains(term) && !unproductive.contains(term))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        UselessElimination {
+            grammar: reduced,
+            unproductive,
+            unreachable,
+            removed,
+        }
+    }
+
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    pub fn into_grammar(self) -> Grammar {
+        self.grammar
+    }
+
+    pub fn unproductive(&self) -> &[Term] {
+        &self.unproductive
+    }
+
+    pub fn unreachable(&self) -> &[Term] {
+        &self.unreachable
+    }
+
+    pub fn removed(&self) -> &[Production] {
+        &self.removed
+    }
+
+    /// Whether the grammar is rewritten at all
+    pub fn is_reduced(&self) -> bool {
+        !self.removed.is_empty()
+    }
+
+    pub fn removal_table(&self) -> Table {
+        let mut builder = Builder::default();
+        builder.push_record(["Symbol", "Reason"]); // header
+        for term in self.unproductive.iter() {
+            builder.push_record([term.to_string(), "unproductive".to_string()]);
+        }
+        for term in self.unreachable.iter() {
+            builder.push_record([term.to_string(), "unreachable".to_string()]);
+        }
+        builder.build()
+    }
+}
+
+impl Display for UselessElimination {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Grammar: \n{}", self.grammar))?;
+        f.write_fmt(format_args!("Removed: \n{}", self.removal_table()))
+    }
+}
+
+/// Keep the alternatives `lhs -> terms` for which `keep` holds,
+/// a production left without any alternative is dropped
+fn retain<F>(grammar: &Grammar, removed: &mut Vec<Production>, keep: F) -> Grammar
+where
+    F: Fn(&Term, &[&Term]) -> bool,
+{
+    let mut productions = vec![];
+    for production in grammar.productions_iter() {
+        let (kept, dropped): (Vec<_>, Vec<_>) = production.rhs_iter().partition(|expr| {
+            let terms = expr.terms_iter().collect::<Vec<_>>();
+            keep(&production.lhs, &terms)
+        });
+        for expr in dropped {
+            debug!("[Useless] Remove {} ::= {}", production.lhs, expr);
+            removed.push(Production::from_parts(
+                production.lhs.clone(),
+                vec![expr.clone()],
+            ));
+        }
+        if !kept.is_empty() {
+            productions.push(Production::from_parts(
+                production.lhs.clone(),
+                kept.into_iter().cloned().collect(),
+            ));
+        }
+    }
+    Grammar::from_parts(productions)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::slr::builder::SLRTableBuilder;
+    use crate::transform::useless::UselessElimination;
+    use crate::utils::symbols;
+    use crate::utils::validate::validate;
+    use bnf::{Grammar, Production, Term};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <S'> ::= <S>
+        <S> ::= <A> <B> | 'a'
+        <A> ::= 'b'
+        <B> ::= 'd' <B>
+        <C> ::= 'c' <S>
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn it_works() {
+        let grammar = grammar();
+        let expected: Grammar = r#"
+        <S'> ::= <S>
+        <S> ::= 'a'
+        "#
+        .parse()
+        .unwrap();
+
+        let reduced = UselessElimination::eliminate(&grammar);
+        assert_eq!(reduced.grammar(), &expected);
+        assert_eq!(reduced.unproductive(), &[Term::from_str("<B>").unwrap()]);
+        // <A> becomes unreachable only once <S> ::= <A> <B> is removed
+        assert_eq!(
+            reduced.unreachable(),
+            &[
+                Term::from_str("'b'").unwrap(),
+                Term::from_str("'c'").unwrap(),
+                Term::from_str("'d'").unwrap(),
+                Term::from_str("<A>").unwrap(),
+                Term::from_str("<C>").unwrap(),
+            ]
+        );
+        assert_eq!(reduced.removed().len(), 4);
+        assert!(validate(reduced.grammar()).is_empty());
+        insta::assert_snapshot!(reduced);
+    }
+
+    #[test]
+    fn smaller_table() {
+        let grammar = grammar();
+        let augmentation = Production::from_str("<S'> ::= <S>").unwrap();
+        let slr = SLRTableBuilder::new(&grammar, &augmentation).build();
+
+        let reduced = UselessElimination::eliminate(&grammar).into_grammar();
+        let reduced_slr = SLRTableBuilder::new(&reduced, &augmentation).build();
+        assert_eq!(symbols(&reduced).count(), 3);
+        assert!(reduced_slr.table.len() < slr.table.len());
+        insta::assert_snapshot!(reduced_slr.parsing_table());
+    }
+
+    #[test]
+    fn already_reduced() {
+        let grammar: Grammar = r#"
+        <E> ::= <E> '+' <T> | <T>
+        <T> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+
+        let reduced = UselessElimination::eliminate(&grammar);
+        assert!(!reduced.is_reduced());
+        assert_eq!(reduced.grammar(), &grammar);
+    }
+}
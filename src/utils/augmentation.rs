@@ -0,0 +1,182 @@
+use crate::utils::{fresh_nonterminal, nonterminals};
+use bnf::{Expression, Grammar, Production, Term};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AugmentationError {
+    /// the augmentation is not of the form `<S'> ::= <S>`
+    Malformed(Production),
+    /// the start symbol `<S>` is not defined by any production
+    UndefinedStart(Term),
+    /// the augmented start symbol `<S'>` appears on a right-hand side
+    StartOnRhs(Term),
+    /// the grammar does not contain exactly the augmentation for `<S'>`
+    NotInGrammar(Production),
+}
+
+impl Display for AugmentationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AugmentationError::Malformed(production) => f.write_fmt(format_args!(
+                "augmentation {} is not of the form <S'> ::= <S>",
+                production
+            )),
+            AugmentationError::UndefinedStart(start) => {
+                f.write_fmt(format_args!("start symbol {} is not defined", start))
+            }
+            AugmentationError::StartOnRhs(start) => f.write_fmt(format_args!(
+                "augmented start symbol {} appears on a right-hand side",
+                start
+            )),
+            AugmentationError::NotInGrammar(production) => f.write_fmt(format_args!(
+                "augmentation {} is not the only production of its lhs in the grammar",
+                production
+            )),
+        }
+    }
+}
+
+impl std::error::Error for AugmentationError {}
+
+/// The start symbol `<S>` of the augmentation `<S'> ::= <S>`
+fn start_of(augmentation: &Production) -> Option<&Term> {
+    let mut rhs = augmentation.rhs_iter();
+    let expr = rhs.next()?;
+    if rhs.next().is_some() || !matches!(augmentation.lhs, Term::Nonterminal(_)) {
+        return None;
+    }
+    let mut terms = expr.terms_iter();
+    match (terms.next(), terms.next()) {
+        (Some(start @ Term::Nonterminal(_)), None) => Some(start),
+        _ => None,
+    }
+}
+
+/// Check that `augmentation` is a production `<S'> ::= <S>` of `grammar`,
+/// where `<S>` is defined and `<S'>` is used nowhere else
+pub fn validate_augmentation(
+    grammar: &Grammar,
+    augmentation: &Production,
+) -> Result<(), AugmentationError> {
+    let start =
+        start_of(augmentation).ok_or_else(|| AugmentationError::Malformed(augmentation.clone()))?;
+
+    if !grammar.productions_iter().any(|prod| prod.lhs == *start) {
+        return Err(AugmentationError::UndefinedStart(start.clone()));
+    }
+
+    if grammar
+        .productions_iter()
+        .flat_map(|prod| prod.rhs_iter())
+        .flat_map(|expr| expr.terms_iter())
+        .any(|term| *term == augmentation.lhs)
+    {
+        return Err(AugmentationError::StartOnRhs(augmentation.lhs.clone()));
+    }
+
+    let alternatives = grammar
+        .productions_iter()
+        .filter(|prod| prod.lhs == augmentation.lhs)
+        .flat_map(|prod| prod.rhs_iter())
+        .collect::<Vec<_>>();
+    if alternatives != augmentation.rhs_iter().collect::<Vec<_>>() {
+        return Err(AugmentationError::NotInGrammar(augmentation.clone()));
+    }
+
+    Ok(())
+}
+
+/// Synthesize the augmentation `<S'> ::= <S>` for the start symbol `<S>`,
+/// return the augmented grammar, whose first production is the augmentation
+///
+/// `<S'>` is `<S>` followed by as many `'` as needed to be a fresh nonterminal
+pub fn augment(
+    grammar: &Grammar,
+    start: &Term,
+) -> Result<(Grammar, Production), AugmentationError> {
+    let Term::Nonterminal(name) = start else {
+        return Err(AugmentationError::UndefinedStart(start.clone()));
+    };
+    if !grammar.productions_iter().any(|prod| prod.lhs == *start) {
+        return Err(AugmentationError::UndefinedStart(start.clone()));
+    }
+
+    let fresh = fresh_nonterminal(name, |fresh| {
+        nonterminals(grammar).any(|term| term == fresh)
+    });
+
+    let augmentation =
+        Production::from_parts(fresh, vec![Expression::from_parts(vec![start.clone()])]);
+    let grammar = Grammar::from_parts(
+        std::iter::once(augmentation.clone())
+            .chain(grammar.productions_iter().cloned())
+            .collect(),
+    );
+
+    Ok((grammar, augmentation))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::augmentation::{augment, validate_augmentation, AugmentationError};
+    use bnf::{Grammar, Production, Term};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <E> ::= <E> '+' <T> | <T>
+        <E'> ::= 'x'
+        <T> ::= 'id'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn synthesize() {
+        let grammar = grammar();
+        let start = Term::from_str("<E>").unwrap();
+        let (augmented, augmentation) = augment(&grammar, &start).unwrap();
+        assert_eq!(augmentation, Production::from_str("<E''> ::= <E>").unwrap());
+        assert_eq!(augmented.productions_iter().next(), Some(&augmentation));
+        assert_eq!(validate_augmentation(&augmented, &augmentation), Ok(()));
+
+        let undefined = Term::from_str("<X>").unwrap();
+        assert_eq!(
+            augment(&grammar, &undefined),
+            Err(AugmentationError::UndefinedStart(undefined))
+        );
+    }
+
+    #[test]
+    fn validate() {
+        let grammar = grammar();
+        [
+            ("<S> ::= <E> | <T>", "Malformed"),
+            ("<S> ::= <E> 'x'", "Malformed"),
+            ("<S> ::= <X>", "UndefinedStart"),
+            ("<E'> ::= <E>", "NotInGrammar"),
+            ("<S> ::= <E>", "NotInGrammar"),
+        ]
+        .iter()
+        .for_each(|(augmentation, error)| {
+            let augmentation = Production::from_str(augmentation).unwrap();
+            let result = validate_augmentation(&grammar, &augmentation).unwrap_err();
+            assert!(format!("{:?}", result).starts_with(error));
+        });
+
+        let grammar: Grammar = r#"
+        <S> ::= <E>
+        <E> ::= <S> '+' 'id' | 'id'
+        "#
+        .parse()
+        .unwrap();
+        let augmentation = Production::from_str("<S> ::= <E>").unwrap();
+        assert_eq!(
+            validate_augmentation(&grammar, &augmentation),
+            Err(AugmentationError::StartOnRhs(
+                Term::from_str("<S>").unwrap()
+            ))
+        );
+    }
+}
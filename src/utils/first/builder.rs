@@ -1,96 +1,79 @@
+use crate::ir::grammar::InternedGrammar;
+use crate::ir::symbol::SymbolId;
 use crate::utils::first::First;
-use crate::utils::{epsilon, symbols};
-use bnf::{Grammar, Production, Term};
 use log::debug;
-use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::BTreeSet;
 
-pub struct FirstBuilder<'grammar> {
-    pub(crate) grammar: &'grammar Grammar,
-    pub(crate) first: RefCell<HashMap<&'grammar Term, HashSet<&'grammar Term>>>,
-    pub(crate) lookup: HashMap<&'grammar Term, &'grammar Production>,
+pub struct FirstBuilder<'a> {
+    pub(crate) grammar: &'a InternedGrammar,
+    pub(crate) nullable: Vec<bool>,
+    pub(crate) first: Vec<BTreeSet<SymbolId>>,
 }
 
-impl<'grammar> FirstBuilder<'grammar> {
-    pub(crate) fn new(grammar: &'grammar Grammar) -> FirstBuilder<'grammar> {
-        let mut first = HashMap::new();
-
-        let lookup = grammar
-            .productions_iter()
-            .map(|production| (&production.lhs, production))
-            .collect::<HashMap<_, _>>();
-
+impl<'a> FirstBuilder<'a> {
+    pub(crate) fn new(grammar: &'a InternedGrammar) -> FirstBuilder<'a> {
+        let symbols = grammar.symbols();
         // initialize the table
-        symbols(grammar)
-            .filter(|term| term != &epsilon()) // epsilon is a special non-terminal
-            .for_each(|term| {
-                first.insert(term, HashSet::new());
-            });
-
-        let first = RefCell::new(first);
+        let first = symbols
+            .iter()
+            .map(|(id, term)| match symbols.is_terminal(id) {
+                true => {
+                    // Rule1: If X is a terminal, then First(X) = { X }
+                    debug!("[First Builder] Rule1: Push {} to First({})", term, term);
+                    BTreeSet::from([id])
+                }
+                false => BTreeSet::new(),
+            })
+            .collect();
 
         FirstBuilder {
             grammar,
+            nullable: vec![false; symbols.len()],
             first,
-            lookup,
         }
     }
 
     fn build_first(&mut self) {
-        symbols(self.grammar)
-            .filter(|term| term != &epsilon())
-            .for_each(|t| {
-                match t {
-                    Term::Terminal(s) => {
-                        // Rule1: If X is a terminal, then First(X) = { X }
-                        self.insert_term(t, t);
-                        debug!("[First Builder] Rule1: Push {} to First({})", s, t);
-                    }
-                    Term::Nonterminal(_) => { /* skip */ }
-                };
-
-                if self.produce_epsilon(t) {
-                    // Rule2: If X is an ε-production, then add ε to First(X)
-                    self.insert_epsilon(t);
-                    debug!("[First Builder] Rule2: Push ε to First({})", t);
-                }
-            });
-
         loop {
             let mut changed = false;
 
-            symbols(self.grammar)
-                .filter(|term| matches!(*term, Term::Nonterminal(_)))
-                .for_each(|lhs| {
-                    debug!("[First Builder] Checking Symbol: {}", lhs);
-                    let production = self.lookup.get(lhs).unwrap();
-                    // Rule3: If X is a non-terminal and X → Y1 Y2 ... Yk,
-                    // then add First(Y1) ∖ {ε} to First(X)
-                    for expr in production.rhs_iter() {
-                        for term in expr
-                            .terms_iter()
-                            .filter(|term| term != &&Term::Terminal("ε".to_string()))
-                        {
-                            // First(Y1) ∖ {ε} to First(X)
-                            changed |= self.insert_first_no_epsilon(&production.lhs, term);
-                            debug!(
-                                "[First Builder] Rule3/4: Push First({}) \\ ε to First({})",
-                                term, production.lhs
-                            );
-                            // terminate (check next expression) if X does NOT produce ε
-                            if !self.produce_epsilon(term) {
-                                debug!("[First Builder] {} does NOT produce ε", term);
-                                break;
-                            }
-                        }
-                        // Rule 5: If X is a non-terminal and X -> Y1 Y2 ... Yk,
-                        // and First(Yi) produce ε for all i, then add ε to First(X)
-                        if expr.terms_iter().all(|term| self.produce_epsilon(term)) {
-                            debug!("[First Builder] Rule5: Push ε to First({})", production.lhs);
-                            changed = self.insert_epsilon(&production.lhs);
-                        }
+            for (_, production) in self.grammar.productions() {
+                let lhs = production.lhs;
+                debug!(
+                    "[First Builder] Checking Symbol: {}",
+                    self.grammar.term(lhs)
+                );
+                // Rule3: If X is a non-terminal and X → Y1 Y2 ... Yk,
+                // then add First(Y1) ∖ {ε} to First(X)
+                for y in production.rhs.iter() {
+                    changed |= self.insert_first(lhs, *y);
+                    debug!(
+                        "[First Builder] Rule3/4: Push First({}) \\ ε to First({})",
+                        self.grammar.term(*y),
+                        self.grammar.term(lhs)
+                    );
+                    // terminate (check next production) if Yi does NOT produce ε
+                    if !self.nullable[y.index()] {
+                        debug!(
+                            "[First Builder] {} does NOT produce ε",
+                            self.grammar.term(*y)
+                        );
+                        break;
                     }
-                });
+                }
+                // Rule2/5: If X -> ε, or X -> Y1 Y2 ... Yk and every Yi produce ε,
+                // then add ε to First(X)
+                if !self.nullable[lhs.index()]
+                    && production.rhs.iter().all(|y| self.nullable[y.index()])
+                {
+                    debug!(
+                        "[First Builder] Rule2/5: Push ε to First({})",
+                        self.grammar.term(lhs)
+                    );
+                    self.nullable[lhs.index()] = true;
+                    changed = true;
+                }
+            }
 
             if !changed {
                 debug!("[First Builder] Unchanged, break!");
@@ -99,108 +82,37 @@ impl<'grammar> FirstBuilder<'grammar> {
         } // End of loop
     }
 
-    fn produce_epsilon(&self, term: &Term) -> bool {
-        let production = self.lookup.get(&term);
-        if production.is_none() {
-            return false;
-        }
-
-        let production = production.unwrap();
-
-        match &production.lhs {
-            Term::Terminal(t) => {
-                if t == "ε" {
-                    return true;
-                }
-            }
-            Term::Nonterminal(nt) => {
-                if nt == "ε" {
-                    return true;
-                }
-            }
-        }
-
-        production
-            .rhs_iter()
-            .map(|expr| {
-                expr.terms_iter().all(|term| match term {
-                    Term::Terminal(t) => t == "ε",
-                    Term::Nonterminal(nt) => nt == "ε",
-                })
-            })
-            .any(|v| v)
-    }
-
-    // Insert term to First(x)
+    /// Insert First(y) into First(x)
     ///
     /// return true if the First(x) changes
     /// otherwise return false
-    fn insert_term(&self, x: &'grammar Term, term: &'grammar Term) -> bool {
-        let mut first = self.first.borrow_mut();
-        // First(x)
-        let first_x = first.get_mut(x).unwrap();
-
-        // Insert term to First(x)
-        let before = first_x.len();
-        first_x.insert(term);
-        let after = first_x.len();
-
-        // check if set changes
-        before != after
-    }
-
-    // Insert epsilon to First(x)
-    fn insert_epsilon(&self, x: &'grammar Term) -> bool {
-        self.insert_term(x, epsilon())
-    }
-
-    /// First(x)
-    fn first(&self, x: &Term) -> HashSet<&'grammar Term> {
-        self.first
-            .borrow()
-            .get(x)
-            .map_or_else(HashSet::new, |set| set.clone())
-    }
-
-    fn insert_set(&self, x: &'grammar Term, set: HashSet<&'grammar Term>) -> bool {
-        let mut first = self.first.borrow_mut();
-        // First(x)
-        let first_x = first.get_mut(x).unwrap();
-
-        // Insert set into First(x)
+    fn insert_first(&mut self, x: SymbolId, y: SymbolId) -> bool {
+        if x == y {
+            return false;
+        }
+        let first_y = self.first[y.index()].clone();
+        let first_x = &mut self.first[x.index()];
         let before = first_x.len();
-        first_x.extend(set);
-        let after = first_x.len();
-
-        // check if set changes
-        before != after
-    }
-
-    /// Insert First(y) \ { ε } into First(x)
-    ///
-    /// return true if the First(x) changes
-    /// otherwise return false
-    fn insert_first_no_epsilon(&self, x: &'grammar Term, y: &'grammar Term) -> bool {
-        // First(y)
-        let mut first_y = self.first(y);
-        // First(y) \ { ε }
-        first_y.remove(epsilon());
-        // Insert First(y) \ { ε } into First(x)
-        self.insert_set(x, first_y)
+        first_x.extend(first_y);
+        before != first_x.len()
     }
 
-    pub(crate) fn build(mut self) -> First<'grammar> {
+    pub(crate) fn build(mut self) -> First {
         self.build_first();
         First {
-            first: self.first.into_inner(),
+            nullable: self.nullable,
+            first: self.first,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::ir::grammar::InternedGrammar;
     use crate::utils::first::builder::FirstBuilder;
     use bnf::Term;
+    use std::collections::BTreeSet;
+    use std::str::FromStr;
 
     #[test]
     fn first() {
@@ -214,14 +126,37 @@ mod tests {
         .parse()
         .unwrap();
 
+        let grammar = InternedGrammar::new(&grammar);
         let first = FirstBuilder::new(&grammar).build();
-        first.first.iter().for_each(|(lhs, rhs)| match lhs {
+        grammar.symbols().iter().for_each(|(id, term)| match term {
             Term::Terminal(_) => {
-                assert_eq!(rhs.len(), 1)
+                assert_eq!(first.first(id).len(), 1)
             }
             Term::Nonterminal(_) => {
-                assert_eq!(rhs.len(), 2)
+                // ε is counted apart
+                assert_eq!(first.first(id).len() + first.is_nullable(id) as usize, 2)
             }
         })
     }
+
+    #[test]
+    fn transitive_epsilon() {
+        let grammar = r#"
+        <S> ::= <A> 'x'
+        <A> ::= <B> <C>
+        <B> ::= 'ε' | 'b'
+        <C> ::= 'ε' | 'c'
+        "#
+        .parse()
+        .unwrap();
+
+        let grammar = InternedGrammar::new(&grammar);
+        let first = FirstBuilder::new(&grammar).build();
+        let id = |term: &str| grammar.id(&Term::from_str(term).unwrap()).unwrap();
+        let terms = |input: &str| input.split_whitespace().map(id).collect::<BTreeSet<_>>();
+        assert_eq!(first.first(id("<A>")), &terms("'b' 'c'"));
+        assert!(first.is_nullable(id("<A>")));
+        assert_eq!(first.first(id("<S>")), &terms("'b' 'c' 'x'"));
+        assert!(!first.is_nullable(id("<S>")));
+    }
 }
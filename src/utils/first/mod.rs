@@ -1,31 +1,70 @@
+use crate::ir::grammar::InternedGrammar;
+use crate::ir::symbol::SymbolId;
+use crate::utils::epsilon;
 use crate::utils::first::builder::FirstBuilder;
-use bnf::{Grammar, Term};
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
+use std::collections::BTreeSet;
 use tabled::builder::Builder;
 use tabled::Table;
 
 mod builder;
 
-pub struct First<'grammar> {
-    pub(crate) first: HashMap<&'grammar Term, HashSet<&'grammar Term>>,
+/// First(X) of every symbol of an [`InternedGrammar`], indexed by `SymbolId`
+///
+/// ε is never in a set, whether X =>* ε is kept in `nullable` instead
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct First {
+    pub(crate) nullable: Vec<bool>,
+    pub(crate) first: Vec<BTreeSet<SymbolId>>,
 }
 
-impl<'grammar> First<'grammar> {
-    pub fn new(grammar: &'grammar Grammar) -> First<'grammar> {
+impl First {
+    pub fn new(grammar: &InternedGrammar) -> First {
         FirstBuilder::new(grammar).build()
     }
 
-    pub fn tabled(&self) -> Table {
+    /// Whether X =>* ε
+    pub fn is_nullable(&self, symbol: SymbolId) -> bool {
+        self.nullable[symbol.index()]
+    }
+
+    /// First(X) without ε
+    pub fn first(&self, symbol: SymbolId) -> &BTreeSet<SymbolId> {
+        &self.first[symbol.index()]
+    }
+
+    /// First(X1 X2 ... Xn) without ε, which is in it if [`First::is_nullable_seq`]
+    pub fn first_of(&self, symbols: impl IntoIterator<Item = SymbolId>) -> BTreeSet<SymbolId> {
+        let mut first = BTreeSet::new();
+        for symbol in symbols {
+            first.extend(self.first(symbol));
+            if !self.is_nullable(symbol) {
+                break;
+            }
+        }
+        first
+    }
+
+    /// Whether every Xi of X1 X2 ... Xn produce ε, true for an empty sequence
+    pub fn is_nullable_seq(&self, symbols: impl IntoIterator<Item = SymbolId>) -> bool {
+        symbols.into_iter().all(|symbol| self.is_nullable(symbol))
+    }
+
+    pub fn tabled(&self, grammar: &InternedGrammar) -> Table {
         let mut table = Builder::new();
         table.push_record(["Term", "First(X)"]);
-        for (term, first) in self
-            .first
-            .iter()
-            .filter(|(term, _)| matches!(term, Term::Nonterminal(_)))
-            .sorted_by(|a, b| a.0.cmp(b.0))
+        for id in grammar
+            .nonterminals()
+            .sorted_by(|a, b| grammar.term(*a).cmp(grammar.term(*b)))
         {
-            table.push_record([term.to_string(), first.iter().sorted().join(", ")]);
+            let first = self
+                .first(id)
+                .iter()
+                .map(|t| grammar.term(*t))
+                .chain(self.is_nullable(id).then(epsilon))
+                .sorted()
+                .join(", ");
+            table.push_record([grammar.term(id).to_string(), first]);
         }
         table.build()
     }
@@ -33,7 +72,10 @@ impl<'grammar> First<'grammar> {
 
 #[cfg(test)]
 mod test {
+    use crate::ir::grammar::InternedGrammar;
     use crate::utils::first::First;
+    use bnf::Term;
+    use std::str::FromStr;
 
     #[test]
     fn it_works() {
@@ -47,9 +89,10 @@ mod test {
         .parse()
         .unwrap();
 
+        let grammar = InternedGrammar::new(&grammar);
         let first = First::new(&grammar);
 
-        insta::assert_snapshot!(first.tabled());
+        insta::assert_snapshot!(first.tabled(&grammar));
     }
 
     #[test]
@@ -64,7 +107,29 @@ mod test {
         "#
         .parse()
         .unwrap();
+        let grammar = InternedGrammar::new(&grammar);
+        let first = First::new(&grammar);
+        insta::assert_snapshot!(first.tabled(&grammar));
+    }
+
+    #[test]
+    fn split_productions() {
+        let grammar = r#"
+        <S> ::= <O> 'v' <C>
+        <O> ::= 'n' | 'ε'
+        <C> ::= <S>
+        <C> ::= 'm'
+        <O> ::= 'o'
+        "#
+        .parse()
+        .unwrap();
+        let grammar = InternedGrammar::new(&grammar);
         let first = First::new(&grammar);
-        insta::assert_snapshot!(first.tabled());
+        let id = |term: &str| grammar.id(&Term::from_str(term).unwrap()).unwrap();
+        assert_eq!(
+            first.first_of([id("<C>")]),
+            ["'m'", "'n'", "'o'", "'v'"].into_iter().map(id).collect()
+        );
+        assert!(!first.is_nullable_seq([id("<C>")]));
     }
 }
@@ -1,95 +1,87 @@
+use crate::ir::grammar::InternedGrammar;
+use crate::ir::symbol::SymbolId;
 use crate::utils::first::First;
 use crate::utils::follow::Follow;
-use crate::utils::symbols;
-use crate::utils::{dollar, epsilon};
-use bnf::{Grammar, Term};
 use log::debug;
-use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::BTreeSet;
 
-pub struct FollowBuilder<'grammar> {
-    pub(crate) grammar: &'grammar Grammar,
-    pub(crate) follow: RefCell<HashMap<&'grammar Term, HashSet<&'grammar Term>>>,
-    pub(crate) first: HashMap<&'grammar Term, HashSet<&'grammar Term>>,
+pub struct FollowBuilder<'a> {
+    pub(crate) grammar: &'a InternedGrammar,
+    pub(crate) follow: Vec<BTreeSet<SymbolId>>,
+    pub(crate) first: &'a First,
 }
 
-impl<'grammar> FollowBuilder<'grammar> {
-    pub(crate) fn new(grammar: &'grammar Grammar) -> FollowBuilder<'grammar> {
-        let mut follow = HashMap::new();
-
-        // initialize the table
-        symbols(grammar)
-            .filter(|term| term != &epsilon()) // epsilon is a special non-terminal
-            .for_each(|term| {
-                follow.insert(term, HashSet::new());
-            });
-
-        let follow = RefCell::new(follow);
-        let first = First::new(grammar).first;
-
+impl<'a> FollowBuilder<'a> {
+    pub(crate) fn new(grammar: &'a InternedGrammar, first: &'a First) -> FollowBuilder<'a> {
         FollowBuilder {
             grammar,
-            follow,
+            // initialize the table
+            follow: vec![BTreeSet::new(); grammar.symbols().len()],
             first,
         }
     }
 
-    fn build_follow(&mut self, start: &'grammar Term) {
+    fn build_follow(&mut self, start: SymbolId) {
         // Rule 1: If X is a start symbol, then Follow(X) = { $ }
-        self.insert_dollar(start);
-        debug!("[Follow Builder] Rule 1: Push $ to Follow({})", start);
+        self.follow[start.index()].insert(self.grammar.end());
+        debug!(
+            "[Follow Builder] Rule 1: Push $ to Follow({})",
+            self.grammar.term(start)
+        );
 
+        let symbols = self.grammar.symbols();
         loop {
             let mut changed = false;
-            for production in self.grammar.productions_iter() {
-                debug!("[Follow Builder] Checking production {}", production.lhs);
-                for expr in production.rhs_iter() {
-                    // Rule 2 checking
-                    let mut prev: Option<&'grammar Term> = None;
-                    for (idx, term) in expr
-                        .terms_iter()
-                        .collect::<Vec<_>>()
-                        .iter()
-                        .rev()
-                        .enumerate()
-                    {
-                        if idx != 0 && matches!(term, Term::Nonterminal(_)) {
-                            let mut first_yi = self
-                                .first
-                                .get(prev.unwrap())
-                                .map_or_else(HashSet::new, |set| set.clone());
-                            first_yi.remove(epsilon());
-                            debug!(
-                                "[Follow Builder] Rule 2: Push First({}) \\ ε to Follow({})",
-                                prev.unwrap(),
-                                term
-                            );
-                            changed |= self.insert_set(term, first_yi);
-                        }
-
-                        prev = Some(term);
-                    } // Rule 2 checking End
-
-                    // Rule 3 checking
-                    for term in expr.terms_iter().collect::<Vec<_>>().iter().rev() {
-                        // if
-                        if matches!(term, Term::Terminal(_)) {
-                            break;
-                        }
-                        // Rule 3: If X -> Y1 Y2 ... Yk,
-                        // then add Follow(X) to Follow(Yk)
+            for (_, production) in self.grammar.productions() {
+                let lhs = production.lhs;
+                debug!(
+                    "[Follow Builder] Checking production {}",
+                    self.grammar.term(lhs)
+                );
+                // Rule 2 checking
+                let terms = &production.rhs;
+                for (idx, term) in terms.iter().enumerate() {
+                    if !symbols.is_nonterminal(*term) {
+                        continue;
+                    }
+                    // Rule 2: If X -> 𝛼 B 𝛽, then add First(𝛽) \ ε to Follow(B),
+                    // First(𝛽) goes through the leading symbols of 𝛽 which produce ε
+                    for next in terms[idx + 1..].iter() {
                         debug!(
-                            "[Follow Builder] Rule 3: Push Follow({}) to Follow({})",
-                            production.lhs, term
+                            "[Follow Builder] Rule 2: Push First({}) \\ ε to Follow({})",
+                            self.grammar.term(*next),
+                            self.grammar.term(*term)
                         );
-                        changed |= self.insert_follow(term, &production.lhs);
+                        changed |= self.insert_set(*term, self.first.first(*next).clone());
 
-                        if !self.first_produce_epsilon(term) {
-                            debug!("[Follow Builder] {} does not produce ε, break", term);
+                        if !self.first.is_nullable(*next) {
                             break;
                         }
-                    } // Rule 3 checking End
-                }
+                    }
+                } // Rule 2 checking End
+
+                // Rule 3 checking
+                for term in terms.iter().rev() {
+                    if symbols.is_terminal(*term) {
+                        break;
+                    }
+                    // Rule 3: If X -> Y1 Y2 ... Yk,
+                    // then add Follow(X) to Follow(Yk)
+                    debug!(
+                        "[Follow Builder] Rule 3: Push Follow({}) to Follow({})",
+                        self.grammar.term(lhs),
+                        self.grammar.term(*term)
+                    );
+                    changed |= self.insert_set(*term, self.follow[lhs.index()].clone());
+
+                    if !self.first.is_nullable(*term) {
+                        debug!(
+                            "[Follow Builder] {} does not produce ε, break",
+                            self.grammar.term(*term)
+                        );
+                        break;
+                    }
+                } // Rule 3 checking End
             }
 
             if !changed {
@@ -98,80 +90,33 @@ impl<'grammar> FollowBuilder<'grammar> {
         }
     }
 
-    fn first_produce_epsilon(&self, term: &Term) -> bool {
-        self.first
-            .get(term)
-            .map_or(false, |first| first.contains(&epsilon()))
-    }
-
-    // Insert term to Follow(x)
+    /// Insert `set` into Follow(x)
     ///
     /// return true if the Follow(x) changes
     /// otherwise return false
-    fn insert_term(&self, x: &'grammar Term, term: &'grammar Term) -> bool {
-        let mut follow = self.follow.borrow_mut();
-        // Follow(x)
-        let follow_x = follow.get_mut(x).unwrap();
-
-        // Insert term to Follow(x)
-        let before = follow_x.len();
-        follow_x.insert(term);
-        let after = follow_x.len();
-
-        // check if set changes
-        before != after
-    }
-
-    // Insert dollar to Follow(x)
-    fn insert_dollar(&self, x: &'grammar Term) -> bool {
-        self.insert_term(x, dollar())
-    }
-
-    // Insert set to Follow(x)
-    fn insert_set(&self, x: &'grammar Term, set: HashSet<&'grammar Term>) -> bool {
-        let mut follow = self.follow.borrow_mut();
-        // Follow(x)
-        let follow_x = follow.get_mut(x).unwrap();
-
-        // Insert set into Follow(x)
+    fn insert_set(&mut self, x: SymbolId, set: BTreeSet<SymbolId>) -> bool {
+        let follow_x = &mut self.follow[x.index()];
         let before = follow_x.len();
         follow_x.extend(set);
-        let after = follow_x.len();
-
-        // check if set changes
-        before != after
+        before != follow_x.len()
     }
 
-    fn follow(&self, x: &Term) -> HashSet<&'grammar Term> {
-        self.follow
-            .borrow()
-            .get(x)
-            .map_or_else(HashSet::new, |set| set.clone())
-    }
-
-    /// Insert Follow(tx) into Follow(rx)
-    pub(crate) fn insert_follow(&mut self, rx: &'grammar Term, tx: &'grammar Term) -> bool {
-        // Follow(tx)
-        let follow_tx = self.follow(tx);
-        debug!("Insert {:?} to Follow({})", follow_tx, rx);
-        // Insert Follow(tx) into Follow(rx)
-        self.insert_set(rx, follow_tx)
-    }
-
-    pub(crate) fn build(mut self, start: &'grammar Term) -> Follow<'grammar> {
+    pub(crate) fn build(mut self, start: SymbolId) -> Follow {
         self.build_follow(start);
         Follow {
-            follow: self.follow.into_inner(),
+            follow: self.follow,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::ir::grammar::InternedGrammar;
+    use crate::utils::first::First;
     use crate::utils::follow::builder::FollowBuilder;
     use crate::utils::follow::Follow;
     use bnf::{Grammar, Term};
-    use std::collections::HashSet;
+    use std::collections::BTreeSet;
 
     use std::str::FromStr;
 
@@ -187,21 +132,47 @@ mod tests {
         grammar
     }
 
-    fn get_follow<'a>(follow: &'a Follow, term: &str) -> HashSet<&'a Term> {
-        let term = Term::from_str(term).unwrap();
-        follow.follow.get(&term).unwrap().clone()
+    fn get_follow(grammar: &InternedGrammar, follow: &Follow, term: &str) -> BTreeSet<Term> {
+        let id = grammar.id(&Term::from_str(term).unwrap()).unwrap();
+        follow
+            .follow_of(id)
+            .iter()
+            .map(|t| grammar.term(*t).clone())
+            .collect()
     }
 
     #[test]
     fn it_works() {
-        let grammar = grammar();
-        let start = Term::Nonterminal("E".to_string());
-        let follow = FollowBuilder::new(&grammar).build(&start);
-
-        assert_eq!(get_follow(&follow, "<E>").len(), 2);
-        assert_eq!(get_follow(&follow, "<E'>").len(), 2);
-        assert_eq!(get_follow(&follow, "<T>").len(), 3);
-        assert_eq!(get_follow(&follow, "<T'>").len(), 3);
-        assert_eq!(get_follow(&follow, "<F>").len(), 4);
+        let grammar = InternedGrammar::new(&grammar());
+        let first = First::new(&grammar);
+        let follow = FollowBuilder::new(&grammar, &first).build(grammar.start().unwrap());
+
+        assert_eq!(get_follow(&grammar, &follow, "<E>").len(), 2);
+        assert_eq!(get_follow(&grammar, &follow, "<E'>").len(), 2);
+        assert_eq!(get_follow(&grammar, &follow, "<T>").len(), 3);
+        assert_eq!(get_follow(&grammar, &follow, "<T'>").len(), 3);
+        assert_eq!(get_follow(&grammar, &follow, "<F>").len(), 4);
+    }
+
+    #[test]
+    fn nullable_suffix() {
+        let grammar: Grammar = r#"
+        <S> ::= <A> <B> 'c'
+        <A> ::= 'a' | 'ε'
+        <B> ::= 'b' | 'ε'
+        "#
+        .parse()
+        .unwrap();
+        let grammar = InternedGrammar::new(&grammar);
+        let first = First::new(&grammar);
+        let follow = FollowBuilder::new(&grammar, &first).build(grammar.start().unwrap());
+
+        let b = Term::from_str("'b'").unwrap();
+        let c = Term::from_str("'c'").unwrap();
+        assert_eq!(
+            get_follow(&grammar, &follow, "<A>"),
+            BTreeSet::from([b, c.clone()])
+        );
+        assert_eq!(get_follow(&grammar, &follow, "<B>"), BTreeSet::from([c]));
     }
 }
@@ -1,36 +1,44 @@
+use crate::ir::grammar::InternedGrammar;
+use crate::ir::symbol::SymbolId;
+use crate::utils::first::First;
 use crate::utils::follow::builder::FollowBuilder;
-use bnf::{Grammar, Term};
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
+use std::collections::BTreeSet;
 use tabled::builder::Builder;
 use tabled::Table;
 
 mod builder;
 
-pub struct Follow<'grammar> {
-    pub(crate) follow: HashMap<&'grammar Term, HashSet<&'grammar Term>>,
+/// Follow(X) of every symbol of an [`InternedGrammar`], indexed by `SymbolId`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Follow {
+    pub(crate) follow: Vec<BTreeSet<SymbolId>>,
 }
 
-impl<'grammar> Follow<'grammar> {
-    pub fn new(grammar: &'grammar Grammar, start: &'grammar Term) -> Follow<'grammar> {
-        FollowBuilder::new(grammar).build(start)
+impl Follow {
+    pub fn new(grammar: &InternedGrammar, first: &First, start: SymbolId) -> Follow {
+        FollowBuilder::new(grammar, first).build(start)
     }
 
-    pub fn follow_of(&self, x: &Term) -> impl Iterator<Item = &&'grammar Term> {
-        // TODO: remove the unwrap?
-        self.follow.get(x).unwrap().iter()
+    /// Follow(X)
+    pub fn follow_of(&self, x: SymbolId) -> &BTreeSet<SymbolId> {
+        &self.follow[x.index()]
     }
 
-    pub fn tabled(&self) -> Table {
+    pub fn tabled(&self, grammar: &InternedGrammar) -> Table {
         let mut table = Builder::new();
         table.push_record(["Term", "Follow(X)"]);
-        for (term, first) in self
-            .follow
-            .iter()
-            .filter(|(term, _)| matches!(term, Term::Nonterminal(_)))
-            .sorted_by(|a, b| a.0.cmp(b.0))
+        for id in grammar
+            .nonterminals()
+            .sorted_by(|a, b| grammar.term(*a).cmp(grammar.term(*b)))
         {
-            table.push_record([term.to_string(), first.iter().sorted().join(", ")]);
+            let follow = self
+                .follow_of(id)
+                .iter()
+                .map(|t| grammar.term(*t))
+                .sorted()
+                .join(", ");
+            table.push_record([grammar.term(id).to_string(), follow]);
         }
         table.build()
     }
@@ -38,6 +46,8 @@ impl<'grammar> Follow<'grammar> {
 
 #[cfg(test)]
 mod tests {
+    use crate::ir::grammar::InternedGrammar;
+    use crate::utils::first::First;
     use crate::utils::follow::Follow;
     use bnf::Term;
     use std::str::FromStr;
@@ -53,9 +63,29 @@ mod tests {
         "#
         .parse()
         .unwrap();
-        let start = Term::from_str("<E>").unwrap();
-        let follow = Follow::new(&grammar, &start);
-        insta::assert_snapshot!(follow.tabled());
+        let grammar = InternedGrammar::new(&grammar);
+        let first = First::new(&grammar);
+        let follow = Follow::new(&grammar, &first, grammar.start().unwrap());
+        insta::assert_snapshot!(follow.tabled(&grammar));
+    }
+
+    #[test]
+    fn unknown_term() {
+        let grammar = r#"
+        <S> ::= <A> 'a'
+        <A> ::= 'b' | <B>
+        "#
+        .parse()
+        .unwrap();
+        assert!(InternedGrammar::try_new(&grammar).is_err());
+
+        // <B> is undefined, but the sets are still computed
+        let grammar = InternedGrammar::new(&grammar);
+        let first = First::new(&grammar);
+        let follow = Follow::new(&grammar, &first, grammar.start().unwrap());
+        let b = grammar.id(&Term::from_str("<B>").unwrap()).unwrap();
+        assert_eq!(follow.follow_of(b).len(), 1);
+        assert_eq!(grammar.id(&Term::from_str("<C>").unwrap()), None);
     }
 
     #[test]
@@ -70,8 +100,9 @@ mod tests {
         "#
         .parse()
         .unwrap();
-        let start = Term::from_str("<P>").unwrap();
-        let follow = Follow::new(&grammar, &start);
-        insta::assert_snapshot!(follow.tabled());
+        let grammar = InternedGrammar::new(&grammar);
+        let first = First::new(&grammar);
+        let follow = Follow::new(&grammar, &first, grammar.start().unwrap());
+        insta::assert_snapshot!(follow.tabled(&grammar));
     }
 }
@@ -0,0 +1,101 @@
+use crate::utils::epsilon;
+use bnf::{Grammar, Term};
+use std::collections::{BTreeSet, HashMap};
+
+type Strings<'grammar> = BTreeSet<Vec<&'grammar Term>>;
+
+/// Every sentence of at most `max_len` terminals derived from the lhs of the first production,
+/// ε is the empty sentence
+///
+/// Useful to check that a transform preserves the language, up to a bounded length
+pub fn sentences(grammar: &Grammar, max_len: usize) -> BTreeSet<Vec<Term>> {
+    let Some(start) = grammar.productions_iter().next().map(|p| &p.lhs) else {
+        return BTreeSet::new();
+    };
+    let mut table = HashMap::<(&Term, usize), Strings>::new();
+    for len in 0..=max_len {
+        // the strings shorter than `len` are final,
+        // the ones of length `len` grow until nothing changes
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in grammar.productions_iter() {
+                for expr in production.rhs_iter() {
+                    let terms = expr
+                        .terms_iter()
+                        .filter(|term| *term != epsilon())
+                        .collect::<Vec<_>>();
+                    let strings = concat(&table, &terms, len);
+                    let entry = table.entry((&production.lhs, len)).or_default();
+                    let before = entry.len();
+                    entry.extend(strings);
+                    changed |= entry.len() != before;
+                }
+            }
+        }
+    }
+    (0..=max_len)
+        .flat_map(|len| table.remove(&(start, len)).unwrap_or_default())
+        .map(|string| string.into_iter().cloned().collect())
+        .collect()
+}
+
+/// The strings of exactly `len` terminals derived from `terms`
+fn concat<'grammar>(
+    table: &HashMap<(&'grammar Term, usize), Strings<'grammar>>,
+    terms: &[&'grammar Term],
+    len: usize,
+) -> Strings<'grammar> {
+    let Some((head, tail)) = terms.split_first() else {
+        return match len {
+            0 => BTreeSet::from([vec![]]),
+            _ => BTreeSet::new(),
+        };
+    };
+    let mut strings = BTreeSet::new();
+    for head_len in 0..=len {
+        let heads = match head {
+            Term::Terminal(_) if head_len == 1 => BTreeSet::from([vec![*head]]),
+            Term::Terminal(_) => continue,
+            Term::Nonterminal(_) => match table.get(&(*head, head_len)) {
+                Some(heads) if !heads.is_empty() => heads.clone(),
+                _ => continue,
+            },
+        };
+        let tails = concat(table, tail, len - head_len);
+        for h in heads.iter() {
+            for t in tails.iter() {
+                strings.insert(h.iter().chain(t.iter()).copied().collect());
+            }
+        }
+    }
+    strings
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::language::sentences;
+    use bnf::Grammar;
+    use itertools::Itertools;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <S> ::= 'a' <S> 'b' | <A>
+        <A> ::= 'ε' | 'c'
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn it_works() {
+        let sentences = sentences(&grammar(), 4)
+            .into_iter()
+            .map(|sentence| sentence.iter().join(" "))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sentences,
+            vec!["", "'a' 'a' 'b' 'b'", "'a' 'b'", "'a' 'c' 'b'", "'c'"]
+        );
+    }
+}
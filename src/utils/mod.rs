@@ -1,10 +1,14 @@
+use crate::error::Error;
 use bnf::{Grammar, Term};
 use itertools::Itertools;
 use once_cell::sync::OnceCell;
 
+pub mod augmentation;
 pub mod first;
 pub mod follow;
+pub mod language;
 pub mod table;
+pub mod validate;
 
 pub fn symbols(grammar: &Grammar) -> impl Iterator<Item = &Term> {
     grammar
@@ -31,11 +35,48 @@ pub fn nonterminals(grammar: &Grammar) -> impl Iterator<Item = &Term> {
         .filter(|term| matches!(*term, Term::Nonterminal(_)))
 }
 
+/// Check that `grammar` has a production, and defines every nonterminal it uses
+pub fn check_grammar(grammar: &Grammar) -> Result<(), Error> {
+    if grammar.productions_iter().next().is_none() {
+        return Err(Error::EmptyGrammar);
+    }
+    match nonterminals(grammar).find(|term| !grammar.productions_iter().any(|p| p.lhs == **term)) {
+        Some(undefined) => Err(Error::UndefinedNonterminal(undefined.clone())),
+        None => Ok(()),
+    }
+}
+
+/// Same as [`check_grammar`], and check that `start` is defined
+pub fn check_start(grammar: &Grammar, start: &Term) -> Result<(), Error> {
+    check_grammar(grammar)?;
+    if !grammar.productions_iter().any(|p| p.lhs == *start) {
+        return Err(Error::StartSymbolMissing(start.clone()));
+    }
+    Ok(())
+}
+
+/// Fail if `grammar` uses the terminal `'$'`, which is reserved for the end of input
+pub fn check_end(grammar: &Grammar) -> Result<(), Error> {
+    match terminals(grammar).any(|term| term == dollar()) {
+        true => Err(Error::ReservedTerminal(dollar().clone())),
+        false => Ok(()),
+    }
+}
+
 pub fn epsilon() -> &'static Term {
     static EPSILON: OnceCell<Term> = OnceCell::new();
     EPSILON.get_or_init(|| Term::Terminal(String::from("ε")))
 }
 
+/// `name` followed by as many `'` as needed to be a nonterminal which is not `taken`
+pub(crate) fn fresh_nonterminal(name: &str, taken: impl Fn(&Term) -> bool) -> Term {
+    let mut fresh = format!("{}'", name);
+    while taken(&Term::Nonterminal(fresh.clone())) {
+        fresh.push('\'');
+    }
+    Term::Nonterminal(fresh)
+}
+
 pub fn dollar() -> &'static Term {
     static DOLLAR: OnceCell<Term> = OnceCell::new();
     DOLLAR.get_or_init(|| Term::Terminal(String::from("$")))
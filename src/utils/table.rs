@@ -20,39 +20,47 @@ where
     }
 }
 
-impl<Row: Hash, Col: Hash> Hash for (dyn KeyPair<Row, Col> + '_) {
+impl<Row: Hash, Col: Hash> Hash for dyn KeyPair<Row, Col> + '_ {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.row().hash(state);
         self.col().hash(state);
     }
 }
 
-impl<Row: Eq, Col: Eq> PartialEq for (dyn KeyPair<Row, Col> + '_) {
+impl<Row: Eq, Col: Eq> PartialEq for dyn KeyPair<Row, Col> + '_ {
     fn eq(&self, other: &Self) -> bool {
         self.row() == other.row() && self.col() == other.col()
     }
 }
 
-impl<Row: Eq, Col: Eq> Eq for (dyn KeyPair<Row, Col> + '_) {}
+impl<Row: Eq, Col: Eq> Eq for dyn KeyPair<Row, Col> + '_ {}
 
 pub struct Table<Row: Eq + Hash, Col: Eq + Hash, V> {
     map: HashMap<(Row, Col), V>,
 }
 
 impl<Row: Eq + Hash, Col: Eq + Hash, V> Table<Row, Col, V> {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Table {
             map: HashMap::new(),
         }
     }
 
-    fn get(&self, row: &Row, col: &Col) -> Option<&V> {
+    pub(crate) fn get(&self, row: &Row, col: &Col) -> Option<&V> {
         self.map.get(&(row, col) as &dyn KeyPair<Row, Col>)
     }
 
-    fn set(&mut self, row: Row, col: Col, v: V) {
+    pub(crate) fn get_mut(&mut self, row: &Row, col: &Col) -> Option<&mut V> {
+        self.map.get_mut(&(row, col) as &dyn KeyPair<Row, Col>)
+    }
+
+    pub(crate) fn set(&mut self, row: Row, col: Col, v: V) {
         self.map.insert((row, col), v);
     }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&Row, &Col, &V)> {
+        self.map.iter().map(|((row, col), v)| (row, col, v))
+    }
 }
 
 impl<Row, Col> KeyPair<Row, Col> for (Row, Col) {
@@ -0,0 +1,234 @@
+use crate::utils::{epsilon, nonterminals, symbols};
+use bnf::{Expression, Grammar, Production, Term};
+use itertools::Itertools;
+use log::warn;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use tabled::builder::Builder;
+use tabled::Table;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// A problem found by [`validate`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Diagnostic {
+    /// the grammar has no production at all
+    EmptyGrammar,
+    /// a nonterminal is used on a right-hand side but defined by no production
+    UndefinedNonterminal(Term),
+    /// a nonterminal cannot be derived from the start symbol
+    UnreachableNonterminal(Term),
+    /// a nonterminal derives no string of terminals
+    UnproductiveNonterminal(Term),
+    /// an alternative in which `'ε'` is mixed with other symbols
+    MisplacedEpsilon(Production),
+    /// an alternative listed more than once for the same nonterminal
+    DuplicateAlternative(Production),
+}
+
+impl Diagnostic {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Diagnostic::UnreachableNonterminal(_) | Diagnostic::DuplicateAlternative(_) => {
+                Severity::Warning
+            }
+            _ => Severity::Error,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity() == Severity::Error
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Diagnostic::EmptyGrammar => f.write_str("the grammar has no production"),
+            Diagnostic::UndefinedNonterminal(term) => {
+                f.write_fmt(format_args!("nonterminal {} is not defined", term))
+            }
+            Diagnostic::UnreachableNonterminal(term) => f.write_fmt(format_args!(
+                "nonterminal {} is unreachable from the start symbol",
+                term
+            )),
+            Diagnostic::UnproductiveNonterminal(term) => f.write_fmt(format_args!(
+                "nonterminal {} derives no terminal string",
+                term
+            )),
+            Diagnostic::MisplacedEpsilon(production) => {
+                f.write_fmt(format_args!("{} mixes 'ε' with other symbols", production))
+            }
+            Diagnostic::DuplicateAlternative(production) => {
+                f.write_fmt(format_args!("{} is a duplicate alternative", production))
+            }
+        }
+    }
+}
+
+/// The nonterminals which derive at least one string of terminals
+pub fn productive(grammar: &Grammar) -> HashSet<&Term> {
+    let mut productive = HashSet::new();
+    // iterate until nothing changes
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for production in grammar.productions_iter() {
+            if productive.contains(&production.lhs) {
+                continue;
+            }
+            if production.rhs_iter().any(|expr| {
+                expr.terms_iter().all(|term| match term {
+                    Term::Terminal(_) => true,
+                    Term::Nonterminal(_) => productive.contains(term),
+                })
+            }) {
+                productive.insert(&production.lhs);
+                changed = true;
+            }
+        }
+    }
+    productive
+}
+
+/// The nonterminals which can be derived from `start`, `start` included
+pub fn reachable<'grammar>(grammar: &'grammar Grammar, start: &Term) -> HashSet<&'grammar Term> {
+    let mut reachable = HashSet::new();
+    let mut stack = symbols(grammar)
+        .filter(|term| *term == start)
+        .collect::<Vec<_>>();
+    while let Some(lhs) = stack.pop() {
+        if !reachable.insert(lhs) {
+            continue;
+        }
+        grammar
+            .productions_iter()
+            .filter(|production| production.lhs == *lhs)
+            .flat_map(|production| production.rhs_iter())
+            .flat_map(|expr| expr.terms_iter())
+            .filter(|term| matches!(term, Term::Nonterminal(_)))
+            .for_each(|term| stack.push(term));
+    }
+    reachable
+}
+
+/// Check `grammar` for mistakes, the lhs of the first production is the start symbol
+pub fn validate(grammar: &Grammar) -> Vec<Diagnostic> {
+    let Some(start) = grammar.productions_iter().next().map(|p| &p.lhs) else {
+        return vec![Diagnostic::EmptyGrammar];
+    };
+    let defined = grammar
+        .productions_iter()
+        .map(|production| &production.lhs)
+        .collect::<HashSet<_>>();
+    let productive = productive(grammar);
+    let reachable = reachable(grammar, start);
+
+    let mut diagnostics = vec![];
+    for term in nonterminals(grammar) {
+        if !defined.contains(term) {
+            diagnostics.push(Diagnostic::UndefinedNonterminal(term.clone()));
+            continue;
+        }
+        if !reachable.contains(term) {
+            diagnostics.push(Diagnostic::UnreachableNonterminal(term.clone()));
+        }
+        if !productive.contains(term) {
+            diagnostics.push(Diagnostic::UnproductiveNonterminal(term.clone()));
+        }
+    }
+
+    // alternatives of split productions are compared together
+    let mut seen = HashSet::<(&Term, &Expression)>::new();
+    for production in grammar.productions_iter() {
+        for expr in production.rhs_iter() {
+            let alternative = || Production::from_parts(production.lhs.clone(), vec![expr.clone()]);
+            if expr.terms_iter().count() > 1 && expr.terms_iter().contains(epsilon()) {
+                diagnostics.push(Diagnostic::MisplacedEpsilon(alternative()));
+            }
+            if !seen.insert((&production.lhs, expr)) {
+                diagnostics.push(Diagnostic::DuplicateAlternative(alternative()));
+            }
+        }
+    }
+
+    diagnostics.iter().for_each(|d| warn!("[Validate] {}", d));
+    diagnostics
+}
+
+pub fn diagnostic_table(diagnostics: &[Diagnostic]) -> Table {
+    let mut builder = Builder::default();
+    builder.push_record(["Severity", "Diagnostic"]); // header
+    for diagnostic in diagnostics {
+        builder.push_record([diagnostic.severity().to_string(), diagnostic.to_string()]);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::validate::{diagnostic_table, validate, Diagnostic, Severity};
+    use bnf::{Grammar, Production, Term};
+    use std::str::FromStr;
+
+    pub fn grammar() -> Grammar {
+        let input = r#"
+        <S> ::= <A> 'a' | <B> | 'b' 'ε'
+        <A> ::= 'c' <A> | 'd' | 'd'
+        <B> ::= 'e' <B>
+        <C> ::= 'f' | <D>
+        <A> ::= 'c' <A>
+        "#;
+        let grammar: Grammar = input.parse().unwrap();
+        grammar
+    }
+
+    #[test]
+    fn it_works() {
+        let diagnostics = validate(&grammar());
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic::UnproductiveNonterminal(Term::from_str("<B>").unwrap()),
+                Diagnostic::UnreachableNonterminal(Term::from_str("<C>").unwrap()),
+                Diagnostic::UndefinedNonterminal(Term::from_str("<D>").unwrap()),
+                Diagnostic::MisplacedEpsilon(Production::from_str("<S> ::= 'b' 'ε'").unwrap()),
+                Diagnostic::DuplicateAlternative(Production::from_str("<A> ::= 'd'").unwrap()),
+                Diagnostic::DuplicateAlternative(Production::from_str("<A> ::= 'c' <A>").unwrap()),
+            ]
+        );
+        assert_eq!(diagnostics[1].severity(), Severity::Warning);
+        insta::assert_snapshot!(diagnostic_table(&diagnostics));
+    }
+
+    #[test]
+    fn well_formed() {
+        let grammar: Grammar = r#"
+        <E> ::= <T> <E'>
+        <E'> ::= '+' <T> <E'> | 'ε'
+        <T> ::= <F> <T'>
+        <T'> ::= '*' <F> <T'> | 'ε'
+        <F> ::= '(' <E> ')' | 'id'
+        "#
+        .parse()
+        .unwrap();
+        assert!(validate(&grammar).is_empty());
+        assert_eq!(
+            validate(&Grammar::from_parts(vec![])),
+            vec![Diagnostic::EmptyGrammar]
+        );
+    }
+}